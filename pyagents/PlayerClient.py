@@ -124,6 +124,8 @@ async def listener(username, soulID):
             print(f"Logged in user: {username} with Soul ID: {soulID}")
             session_credential = response.strip()
             set_state("gameplay")
+            # Ask for JSON packages in envelopes, so sense packages can be told apart from visual packages
+            await websocket.send(json.dumps({"type": "SetEnvelope", "payload": {"soul_id": session_credential, "enabled": True}}))
         else:
             print(f"Recieved: {response.strip()}")
 
@@ -139,8 +141,10 @@ async def listener(username, soulID):
                     try:
                         data = json.loads(msg.decode("utf-8"))
                         print("Received JSON:", data)
-                        render_world(data)
-                    except json.JSONDecodeError:
+                        # With envelopes on, JSON packages come wrapped in a {"Visual": ...} or {"Sense": ...} envelope
+                        if "Visual" in data:
+                            render_world(data["Visual"])
+                    except (json.JSONDecodeError, UnicodeDecodeError):
                         print("not valid JSON:", msg)
                 else:
                     print("Received non-JSON message:", msg)
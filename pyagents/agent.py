@@ -57,6 +57,10 @@ async def agent():
         credential = response.strip()
         print(f"Logged in, received credential: {credential}")
 
+        # Ask for JSON packages in envelopes, so sense packages can be told apart from visual packages
+        envelope_msg = {"type": "SetEnvelope", "payload": {"soul_id": credential, "enabled": True}}
+        await websocket.send(json.dumps(envelope_msg))
+
         async def send_loop():
             while True:
                 user_input = await read_input()
@@ -104,12 +108,21 @@ async def agent():
                 try:
                     msg = await asyncio.wait_for(websocket.recv(), timeout=0.05)
                     if isinstance(msg, bytes):
+                        if msg.startswith(b"VP"):
+                            print("Received compact visual package,", len(msg), "bytes")
+                            continue
                         try:
                             data = json.loads(msg.decode("utf-8"))
-                            print("Received JSON:", data)
-                            render_world(data)
                         except json.JSONDecodeError:
                             print("not valid JSON:", msg)
+                            continue
+                        # With envelopes on, every JSON package comes wrapped in a {"Visual": ...} or {"Sense": ...} envelope
+                        if "Visual" in data:
+                            render_world(data["Visual"])
+                        elif "Sense" in data:
+                            print("Received sense package:", data["Sense"])
+                        else:
+                            print("Received unknown package:", data)
                     else:
                         print("Received non-JSON message:", msg)
                 except asyncio.TimeoutError:
@@ -129,7 +142,8 @@ def render_world(squares):
         y = sq["y"]
         content = sq["content"]
 
-        rect = pygame.Rect(x * TILE_SIZE, y * TILE_SIZE, TILE_SIZE, TILE_SIZE)
+        # Local +y points north, screen y grows downwards
+        rect = pygame.Rect(x * TILE_SIZE, -y * TILE_SIZE, TILE_SIZE, TILE_SIZE)
 
         if "WorldCell" in content:
             val = content["WorldCell"]
@@ -0,0 +1,112 @@
+// This file houses the ownership model. Every critter is the connected component of cells rooted at its Soul,
+// and anything cut off from its Soul stops belonging to it.
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::cell_def;
+use crate::lifecycle;
+use crate::utils;
+use crate::WorldData;
+use crate::ServerData;
+use crate::chunks::ChunkedGrid;
+use cell_def::{CellKind, SoulHandle};
+
+// Flood fills from (x, y) over the 8-neighbourhood and returns every connected cell owned by soul_id
+pub fn connected_cells(world_data: &WorldData, soul_id: &str, x: usize, y: usize) -> HashSet<(usize, usize)> {
+    let critter_layer = &world_data.critter_layer;
+    let mut body = HashSet::new();
+    let Some(owner) = world_data.souls.handle(soul_id) else {
+        return body;
+    };
+    let mut frontier = vec![(x, y)];
+
+    while let Some((cx, cy)) = frontier.pop() {
+        if critter_layer.is_empty((cx, cy)) || critter_layer.owner((cx, cy)) != owner || !body.insert((cx, cy)) {
+            continue;
+        }
+
+        for dy in -1i32..=1 {
+            for dx in -1i32..=1 {
+                if let Some(neighbour) = world_data.resolve(cx as i32 + dx, cy as i32 + dy) {
+                    frontier.push(neighbour);
+                }
+            }
+        }
+    }
+
+    body
+}
+
+// The connected body of a living soul, None if the soul has no location
+pub fn soul_body(world_data: &WorldData, soul_id: &str) -> Option<HashSet<(usize, usize)>> {
+    let &(_, soul_x, soul_y) = world_data.soul_locations.iter().find(|(s, _, _)| s == soul_id)?;
+    Some(connected_cells(world_data, soul_id, soul_x as usize, soul_y as usize))
+}
+
+// True if (x, y) is next to, but not on, one of the given squares
+pub fn touches(world_data: &WorldData, squares: &HashSet<(usize, usize)>, x: i32, y: i32) -> bool {
+    for dy in -1i32..=1 {
+        for dx in -1i32..=1 {
+            if (dx, dy) != (0, 0) && world_data.resolve(x + dx, y + dy).is_some_and(|square| squares.contains(&square)) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+// Turns every cell of a living soul that is no longer connected to its Soul cell into corpse matter.
+// Returns how many cells were cut off.
+pub fn orphan_detached(world_data: &mut WorldData, soul_id: &str) -> usize {
+    let Some(&(_, soul_x, soul_y)) = world_data.soul_locations.iter().find(|(s, _, _)| s == soul_id) else {
+        return 0;
+    };
+    let Some(owner) = world_data.souls.handle(soul_id) else {
+        return 0;
+    };
+
+    let body = connected_cells(world_data, soul_id, soul_x as usize, soul_y as usize);
+    let mut detached = 0;
+
+    for square in world_data.critter_layer.occupied_squares() {
+        let cell = world_data.critter_layer.get(square);
+        if !cell.is_empty() && cell.owner == owner && !body.contains(&square) {
+            world_data.critter_layer.set(square, lifecycle::corpse(cell.energy));
+            detached += 1;
+        }
+    }
+
+    detached
+}
+
+// Per tick check that every critter cell still hangs off a living Soul. Cut off cells, and cells of souls that no longer
+// exist, become corpse matter. Souls that lost cells are told how many.
+pub async fn orphan_all_detached(world_data: &mut WorldData, server_data: &Arc<Mutex<ServerData>>) {
+    let mut attached = ChunkedGrid::new(world_data.width(), world_data.height(), false);
+    for (soul_id, soul_x, soul_y) in world_data.soul_locations.iter() {
+        for square in connected_cells(world_data, soul_id, *soul_x as usize, *soul_y as usize) {
+            attached[square] = true;
+        }
+    }
+
+    let mut lost: Vec<(SoulHandle, usize)> = Vec::new();
+    for square in world_data.critter_layer.occupied_squares() {
+        let cell = world_data.critter_layer.get(square);
+        if cell.is_empty() || cell.kind == CellKind::Corpse || attached[square] {
+            continue;
+        }
+
+        match lost.iter_mut().find(|(s, _)| *s == cell.owner) {
+            Some((_, count)) => *count += 1,
+            None => lost.push((cell.owner, 1)),
+        }
+        world_data.critter_layer.set(square, lifecycle::corpse(cell.energy));
+    }
+
+    for (owner, count) in lost {
+        let soul_id = world_data.souls.name(owner).to_string();
+        println!("{} cells of {} were cut off from their soul", count, soul_id);
+        utils::send_text(world_data, server_data, &soul_id, format!("{} of your cells were cut off from your soul and died", count)).await;
+    }
+}
@@ -1,7 +1,7 @@
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Deserialize, Serializer, Deserializer};
 
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[derive(Serialize, Deserialize)]
   pub enum CellKind {
         Empty,
@@ -13,6 +13,10 @@ use serde::{Serialize, Deserialize};
         Muscle,
         Anchor,
         Armor,
+        Corpse, // Neutral matter left behind by a dead soul, never built directly
+        Nose, // Smells which way the food is
+        Whisker, // Feels foreign cells touching the critter
+        Ear, // Hears other critters' Muscles at work
     }
 
 impl CellKind {
@@ -27,33 +31,171 @@ impl CellKind {
             "Muscle" => Some(CellKind::Muscle),
             "Anchor" => Some(CellKind::Anchor),
             "Armor" => Some(CellKind::Armor),
+            "Corpse" => Some(CellKind::Corpse),
+            "Nose" => Some(CellKind::Nose),
+            "Whisker" => Some(CellKind::Whisker),
+            "Ear" => Some(CellKind::Ear),
             _ => None,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+// Which way a cell faces, kept in a single byte. The low 7 bits are a binary angle in 128ths of a turn clockwise from
+// north, so the eight compass points land exactly on multiples of 16. C is centered and faces nowhere in particular.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Orientation(u8);
+
+impl Orientation {
+    pub const N: Orientation = Orientation(0);
+    pub const NE: Orientation = Orientation(16);
+    pub const E: Orientation = Orientation(32);
+    pub const SE: Orientation = Orientation(48);
+    pub const S: Orientation = Orientation(64);
+    pub const SW: Orientation = Orientation(80);
+    pub const W: Orientation = Orientation(96);
+    pub const NW: Orientation = Orientation(112);
+    pub const C: Orientation = Orientation(0x80);
+
+    const STEPS: u32 = 128; // Binary angle steps in a full turn
+
+    // Accepts a compass point ("N", "NE", ...), "C", or a whole number of degrees clockwise from north. Degrees are
+    // rounded to the nearest of the 128 steps, which are about 2.8 degrees apart. "90" and "E" are the same
+    // orientation, but "30" is stored as 31 degrees and reads back that way.
+    pub fn from_input_string(input_string: &str) -> Option<Self> {
+        match input_string {
+            "N" => Some(Orientation::N),
+            "S" => Some(Orientation::S),
+            "E" => Some(Orientation::E),
+            "W" => Some(Orientation::W),
+            "C" => Some(Orientation::C),
+            "NE" => Some(Orientation::NE),
+            "NW" => Some(Orientation::NW),
+            "SE" => Some(Orientation::SE),
+            "SW" => Some(Orientation::SW),
+            _ => {
+                let degrees = input_string.trim().parse::<i32>().ok()?.rem_euclid(360) as u16;
+                Some(Orientation::from_bearing(degrees))
+            }
+        }
+    }
+
+    pub fn from_bearing(degrees: u16) -> Self {
+        let step = ((degrees % 360) as u32 * Self::STEPS + 180) / 360;
+        Orientation((step % Self::STEPS) as u8)
+    }
+
+    // Degrees clockwise from north, None for a centered cell. Everything that needs a heading works from this.
+    pub fn bearing(self) -> Option<u16> {
+        if self == Orientation::C {
+            return None;
+        }
+        Some(((self.0 as u32 * 360 + Self::STEPS / 2) / Self::STEPS) as u16 % 360)
+    }
+
+    // Global grid offset of the square a cell is facing. The heading is rounded to the nearest compass point first, so
+    // the switch to the next neighbour happens halfway between two of them, at 22.5 degrees past a compass point.
+    // North is up, so it decreases y.
+    pub fn offset(self) -> (i32, i32) {
+        const OFFSETS: [(i32, i32); 8] = [(0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1)];
+        if self == Orientation::C {
+            return (0, 0);
+        }
+        let steps_per_octant = Self::STEPS / 8;
+        let octant = (self.0 as u32 + steps_per_octant / 2) / steps_per_octant % 8;
+        OFFSETS[octant as usize]
+    }
+}
+
+// How an orientation reads in JSON, the same shape clients were sent before orientations fit in a byte. Bearings are
+// whole degrees rounded from the stored step, so they can differ by a degree or two from the ones a client asked for.
+#[derive(Serialize, Deserialize)]
+enum OrientationName {
+    N,
+    S,
+    E,
+    W,
+    C,
+    NE,
+    NW,
+    SE,
+    SW,
+    Bearing(u16),
+}
+
+// Saves store the raw byte, JSON spells out the compass point or the bearing in degrees
+impl Serialize for Orientation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return serializer.serialize_u8(self.0);
+        }
+        let name = match *self {
+            Orientation::N => OrientationName::N,
+            Orientation::S => OrientationName::S,
+            Orientation::E => OrientationName::E,
+            Orientation::W => OrientationName::W,
+            Orientation::C => OrientationName::C,
+            Orientation::NE => OrientationName::NE,
+            Orientation::NW => OrientationName::NW,
+            Orientation::SE => OrientationName::SE,
+            Orientation::SW => OrientationName::SW,
+            other => OrientationName::Bearing(other.bearing().unwrap_or(0)),
+        };
+        name.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Orientation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if !deserializer.is_human_readable() {
+            let byte = u8::deserialize(deserializer)?;
+            return Ok(if byte & 0x80 != 0 { Orientation::C } else { Orientation(byte) });
+        }
+        Ok(match OrientationName::deserialize(deserializer)? {
+            OrientationName::N => Orientation::N,
+            OrientationName::S => Orientation::S,
+            OrientationName::E => Orientation::E,
+            OrientationName::W => Orientation::W,
+            OrientationName::C => Orientation::C,
+            OrientationName::NE => Orientation::NE,
+            OrientationName::NW => Orientation::NW,
+            OrientationName::SE => Orientation::SE,
+            OrientationName::SW => Orientation::SW,
+            OrientationName::Bearing(degrees) => Orientation::from_bearing(degrees),
+        })
+    }
+}
+
+// Compact stand-in for a soul id, the names live in the SoulTable in WorldData
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+pub struct SoulHandle(pub u32);
+
+impl SoulHandle {
+    pub const NONE: SoulHandle = SoulHandle(0); // Owner of empty cells and corpses
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[derive(Serialize, Deserialize)]
     pub struct Cell {
-        pub id: String,
+        pub owner: SoulHandle,
         pub kind: CellKind,
         pub energy: i16,
-        pub orientation: String,
+        pub orientation: Orientation,
     }
 
     impl Cell {
         pub fn empty() -> Self {
             Self {
-                id: "Null".to_string(),
+                owner: SoulHandle::NONE,
                 kind: CellKind::Empty,
                 energy: 0,
-                orientation: "C".to_string(),
+                orientation: Orientation::C,
             }
         }
 
-        pub fn new(id: String, kind: CellKind, energy: i16, orientation: String) -> Self {
+        pub fn new(owner: SoulHandle, kind: CellKind, energy: i16, orientation: Orientation) -> Self {
             Self {
-                id,
+                owner,
                 kind,
                 energy,
                 orientation,
@@ -65,9 +207,54 @@ impl CellKind {
         }
 
         pub fn valid_dir(direction: &str) -> bool {
-            match direction {
-                "N" | "S" | "E" | "W" | "C" => true,
-                _ => false,
-            }
+            Orientation::from_input_string(direction).is_some()
+        }
+
+        // Only cells that act in a direction can be rotated
+        pub fn can_rotate(&self) -> bool {
+            matches!(self.kind, CellKind::Eyeball | CellKind::Mouth | CellKind::Muscle | CellKind::Butt)
+        }
+    }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compass_points_face_their_neighbour() {
+        let expected = [
+            (Orientation::N, (0, -1)),
+            (Orientation::NE, (1, -1)),
+            (Orientation::E, (1, 0)),
+            (Orientation::SE, (1, 1)),
+            (Orientation::S, (0, 1)),
+            (Orientation::SW, (-1, 1)),
+            (Orientation::W, (-1, 0)),
+            (Orientation::NW, (-1, -1)),
+            (Orientation::C, (0, 0)),
+        ];
+        for (orientation, offset) in expected {
+            assert_eq!(orientation.offset(), offset, "{:?}", orientation);
         }
     }
+
+    // 8 steps are 22.5 degrees and 24 steps are 67.5 degrees, headings exactly halfway go clockwise
+    #[test]
+    fn headings_switch_neighbour_halfway_between_compass_points() {
+        assert_eq!(Orientation(7).offset(), (0, -1));
+        assert_eq!(Orientation(8).offset(), (1, -1));
+        assert_eq!(Orientation(23).offset(), (1, -1));
+        assert_eq!(Orientation(24).offset(), (1, 0));
+        assert_eq!(Orientation(120).offset(), (0, -1));
+        assert_eq!(Orientation::from_bearing(25).offset(), (1, -1));
+        assert_eq!(Orientation::from_bearing(65).offset(), (1, -1));
+        assert_eq!(Orientation::from_bearing(340).offset(), (0, -1));
+    }
+
+    #[test]
+    fn degrees_round_to_the_nearest_step() {
+        assert_eq!(Orientation::from_input_string("90"), Some(Orientation::E));
+        assert_eq!(Orientation::from_input_string("-45"), Some(Orientation::NW));
+        assert_eq!(Orientation::from_input_string("30").and_then(Orientation::bearing), Some(31));
+    }
+}
@@ -0,0 +1,227 @@
+// This file houses the chunked grid every world layer is stored in. Squares are grouped into CHUNK_SIZE x CHUNK_SIZE chunks
+// that are only allocated once something other than the fill value is written to them, and every chunk remembers whether
+// it was written to so the per tick passes can skip chunks where nothing is happening.
+use serde::{Serialize, Deserialize};
+use std::ops::{Index, IndexMut};
+
+pub const CHUNK_SIZE: usize = 64;
+
+#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize)]
+struct Chunk<T> {
+    squares: Vec<T>, // CHUNK_SIZE * CHUNK_SIZE squares, row by row
+    dirty: bool, // Written to since the last time the chunk was marked clean
+}
+
+#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct ChunkedGrid<T> {
+    width: usize,
+    height: usize,
+    fill: T, // Value of every square in a chunk that was never allocated
+    chunks: Vec<Option<Chunk<T>>>, // Row by row, unallocated chunks are saved as a single byte
+}
+
+impl<T: Clone + PartialEq> ChunkedGrid<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> Self {
+        let count = width.div_ceil(CHUNK_SIZE) * height.div_ceil(CHUNK_SIZE);
+        Self {
+            width,
+            height,
+            fill,
+            chunks: vec![None; count],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn chunks_wide(&self) -> usize {
+        self.width.div_ceil(CHUNK_SIZE)
+    }
+
+    // Chunk index and index within the chunk of a square, panics off the grid just like indexing a Vec would
+    fn locate(&self, x: usize, y: usize) -> (usize, usize) {
+        assert!(x < self.width && y < self.height, "square ({}, {}) is outside a {}x{} grid", x, y, self.width, self.height);
+        let chunk = (y / CHUNK_SIZE) * self.chunks_wide() + x / CHUNK_SIZE;
+        (chunk, (y % CHUNK_SIZE) * CHUNK_SIZE + x % CHUNK_SIZE)
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> &T {
+        let (chunk, offset) = self.locate(x, y);
+        match &self.chunks[chunk] {
+            Some(chunk) => &chunk.squares[offset],
+            None => &self.fill,
+        }
+    }
+
+    // Allocates the chunk if needed and marks it dirty
+    pub fn get_mut(&mut self, x: usize, y: usize) -> &mut T {
+        let (chunk, offset) = self.locate(x, y);
+        let fill = &self.fill;
+        let chunk = self.chunks[chunk].get_or_insert_with(|| Chunk {
+            squares: vec![fill.clone(); CHUNK_SIZE * CHUNK_SIZE],
+            dirty: true,
+        });
+        chunk.dirty = true;
+        &mut chunk.squares[offset]
+    }
+
+    // Like writing through get_mut, but writing the fill value into an unallocated chunk leaves it unallocated
+    pub fn set(&mut self, x: usize, y: usize, value: T) {
+        let (chunk, _) = self.locate(x, y);
+        if self.chunks[chunk].is_none() && value == self.fill {
+            return;
+        }
+        *self.get_mut(x, y) = value;
+    }
+
+    // Every square of the chunk that lies on the grid, chunks on the right and bottom edges can be partly off it
+    pub fn chunk_squares(&self, chunk: usize) -> impl Iterator<Item = (usize, usize)> + use<T> {
+        let x0 = (chunk % self.chunks_wide()) * CHUNK_SIZE;
+        let y0 = (chunk / self.chunks_wide()) * CHUNK_SIZE;
+        let (x1, y1) = ((x0 + CHUNK_SIZE).min(self.width), (y0 + CHUNK_SIZE).min(self.height));
+        (y0..y1).flat_map(move |y| (x0..x1).map(move |x| (x, y)))
+    }
+
+    pub fn chunk_of(&self, x: usize, y: usize) -> usize {
+        self.locate(x, y).0
+    }
+
+    pub fn allocated_chunks(&self) -> Vec<usize> {
+        (0..self.chunks.len()).filter(|&chunk| self.chunks[chunk].is_some()).collect()
+    }
+
+    pub fn dirty_chunks(&self) -> Vec<usize> {
+        (0..self.chunks.len()).filter(|&chunk| self.chunks[chunk].as_ref().is_some_and(|c| c.dirty)).collect()
+    }
+
+    pub fn mark_clean(&mut self, chunk: usize) {
+        if let Some(chunk) = &mut self.chunks[chunk] {
+            chunk.dirty = false;
+        }
+    }
+
+    // Every square in an allocated chunk, squares in unallocated chunks all hold the fill value
+    pub fn allocated_squares(&self) -> Vec<(usize, usize)> {
+        self.allocated_chunks().into_iter().flat_map(|chunk| self.chunk_squares(chunk)).collect()
+    }
+
+    // Every square in an allocated chunk along with its value, read straight off the chunk without looking each square up
+    pub fn allocated_values(&self) -> impl Iterator<Item = ((usize, usize), &T)> {
+        self.allocated_chunks().into_iter().flat_map(move |chunk| {
+            let squares = &self.chunks[chunk].as_ref().unwrap().squares;
+            self.chunk_squares(chunk).map(move |(x, y)| ((x, y), &squares[(y % CHUNK_SIZE) * CHUNK_SIZE + x % CHUNK_SIZE]))
+        })
+    }
+
+    // Frees every allocated chunk that only holds the fill value again
+    pub fn free_unused(&mut self) {
+        let fill = &self.fill;
+        for chunk in self.chunks.iter_mut() {
+            if chunk.as_ref().is_some_and(|c| c.squares.iter().all(|square| square == fill)) {
+                *chunk = None;
+            }
+        }
+    }
+
+    pub fn allocated_count(&self) -> usize {
+        self.chunks.iter().filter(|chunk| chunk.is_some()).count()
+    }
+}
+
+impl<T: Clone + PartialEq> Index<(usize, usize)> for ChunkedGrid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        self.get(x, y)
+    }
+}
+
+impl<T: Clone + PartialEq> IndexMut<(usize, usize)> for ChunkedGrid<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+        self.get_mut(x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edge_chunks_cover_the_rest_of_the_grid() {
+        let mut grid = ChunkedGrid::new(CHUNK_SIZE + 3, 2 * CHUNK_SIZE + 1, 0u8);
+        assert_eq!(grid.chunks.len(), 2 * 3);
+
+        let corner = (CHUNK_SIZE + 2, 2 * CHUNK_SIZE);
+        grid[corner] = 9;
+        assert_eq!(grid[corner], 9);
+
+        let chunk = grid.chunk_of(corner.0, corner.1);
+        assert_eq!(chunk, 5);
+        let squares: Vec<(usize, usize)> = grid.chunk_squares(chunk).collect();
+        assert_eq!(squares.len(), 3);
+        assert!(squares.iter().all(|&(x, y)| (CHUNK_SIZE..CHUNK_SIZE + 3).contains(&x) && y == 2 * CHUNK_SIZE));
+        assert_eq!(grid.allocated_squares().len(), 3);
+        assert_eq!(grid.allocated_values().filter(|(_, value)| **value == 9).count(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn squares_off_an_edge_chunk_panic() {
+        let grid = ChunkedGrid::new(CHUNK_SIZE + 3, CHUNK_SIZE, 0u8);
+        grid.get(CHUNK_SIZE + 3, 0);
+    }
+
+    #[test]
+    fn setting_the_fill_value_does_not_allocate() {
+        let mut grid = ChunkedGrid::new(100, 100, 7u8);
+        grid.set(10, 10, 7);
+        assert_eq!(grid.allocated_count(), 0);
+        assert_eq!(grid[(10, 10)], 7);
+
+        grid.set(10, 10, 8);
+        assert_eq!(grid.allocated_count(), 1);
+        grid.set(10, 10, 7);
+        assert_eq!(grid.allocated_count(), 1);
+        assert_eq!(grid[(10, 10)], 7);
+    }
+
+    #[test]
+    fn free_unused_only_frees_chunks_back_at_the_fill_value() {
+        let mut grid = ChunkedGrid::new(200, 200, 0u8);
+        grid[(1, 1)] = 5;
+        grid[(100, 100)] = 5;
+        grid[(100, 100)] = 0;
+
+        grid.free_unused();
+        assert_eq!(grid.allocated_count(), 1);
+        assert_eq!(grid.allocated_chunks(), vec![grid.chunk_of(1, 1)]);
+        assert_eq!(grid[(1, 1)], 5);
+        assert_eq!(grid[(100, 100)], 0);
+    }
+
+    #[test]
+    fn writes_mark_chunks_dirty_until_marked_clean() {
+        let mut grid = ChunkedGrid::new(200, 200, 0u8);
+        assert!(grid.dirty_chunks().is_empty());
+
+        grid[(1, 1)] = 5;
+        *grid.get_mut(150, 150) += 1;
+        let (first, second) = (grid.chunk_of(1, 1), grid.chunk_of(150, 150));
+        assert_eq!(grid.dirty_chunks(), vec![first, second]);
+
+        grid.mark_clean(first);
+        assert_eq!(grid.dirty_chunks(), vec![second]);
+        let _ = grid[(1, 1)];
+        assert_eq!(grid.dirty_chunks(), vec![second]);
+
+        grid.set(2, 2, 3);
+        assert_eq!(grid.dirty_chunks(), vec![first, second]);
+    }
+}
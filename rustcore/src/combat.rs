@@ -0,0 +1,160 @@
+// This file houses the combat subsystem. Mouths bite enemy cells, Armor soaks up or throws back part of the damage.
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::cell_def;
+use crate::lifecycle;
+use crate::utils;
+use crate::WorldData;
+use crate::BPs;
+use crate::ServerData;
+use cell_def::{Cell, CellKind};
+
+// Everything that happened in a single bite, used to report the fight to both souls
+#[derive(Debug)]
+pub struct BiteOutcome {
+    pub victim_id: String,
+    pub victim_kind: CellKind,
+    pub victim_x: i32,
+    pub victim_y: i32,
+    pub damage: i16, // Damage that actually reached the victim cell
+    pub absorbed: i16, // Damage soaked up by Armor
+    pub reflected: i16, // Damage Armor threw back at the attacking mouth
+    pub eaten: i16, // Energy torn out of the victim, at most the energy it had left
+    pub victim_destroyed: bool,
+    pub mouth_destroyed: bool,
+}
+
+// Bites the enemy cell at (victim_x, victim_y) with the mouth at (mouth_x, mouth_y). Destroyed cells are cleared from the
+// critter layer and a destroyed Soul kills its soul.
+pub fn bite(world_data: &mut WorldData, mouth_x: i32, mouth_y: i32, victim_x: i32, victim_y: i32, damage: i16, b_ps: &BPs) -> BiteOutcome {
+    let victim_square = (victim_x as usize, victim_y as usize);
+    let mut victim = world_data.critter_layer.get(victim_square);
+    let victim_id = world_data.souls.name(victim.owner).to_string();
+    let victim_kind = victim.kind;
+
+    let (absorbed, reflected) = if victim_kind == CellKind::Armor {
+        (
+            (damage as i32 * b_ps.ArmorAbsorb as i32 / 100) as i16,
+            (damage as i32 * b_ps.ArmorReflect as i32 / 100) as i16,
+        )
+    } else {
+        (0, 0)
+    };
+    let damage = (damage - absorbed - reflected).max(0);
+
+    let eaten = damage.min(victim.energy).max(0);
+    victim.energy = victim.energy.saturating_sub(damage);
+    let victim_destroyed = victim.energy <= 0;
+    if victim_destroyed {
+        victim = Cell::empty();
+    }
+    world_data.critter_layer.set(victim_square, victim);
+    if victim_destroyed && victim_kind == CellKind::Soul {
+        lifecycle::kill_soul(world_data, &victim_id, b_ps);
+    }
+
+    let mouth_square = (mouth_x as usize, mouth_y as usize);
+    let mouth_energy = world_data.critter_layer.energy_mut(mouth_square);
+    *mouth_energy = mouth_energy.saturating_sub(reflected);
+    let mouth_destroyed = *mouth_energy <= 0;
+    if mouth_destroyed {
+        world_data.critter_layer.clear(mouth_square);
+    }
+
+    BiteOutcome {
+        victim_id,
+        victim_kind,
+        victim_x,
+        victim_y,
+        damage,
+        absorbed,
+        reflected,
+        eaten,
+        victim_destroyed,
+        mouth_destroyed,
+    }
+}
+
+// Tells both the attacker and the victim how a bite went
+pub async fn report_bite(world_data: &WorldData, attacker_id: &str, outcome: &BiteOutcome, server_data: &Arc<Mutex<ServerData>>) {
+    let mut attacker_report = format!(
+        "Bit an enemy {:?} for {} damage ({} absorbed, {} reflected)",
+        outcome.victim_kind, outcome.damage, outcome.absorbed, outcome.reflected
+    );
+    if outcome.victim_destroyed {
+        attacker_report.push_str(", it was destroyed");
+    }
+    if outcome.mouth_destroyed {
+        attacker_report.push_str(", your Mouth was destroyed by the recoil");
+    }
+    utils::send_text(world_data, server_data, attacker_id, attacker_report).await;
+
+    let victim_report = if outcome.victim_destroyed && outcome.victim_kind == CellKind::Soul {
+        "Your soul was eaten".to_string()
+    } else {
+        let (local_x, local_y) = world_data.global_to_local(&outcome.victim_id, outcome.victim_x, outcome.victim_y);
+        let mut report = format!(
+            "Your {:?} at ({}, {}) was bitten for {} damage ({} absorbed, {} reflected)",
+            outcome.victim_kind, local_x, local_y, outcome.damage, outcome.absorbed, outcome.reflected
+        );
+        if outcome.victim_destroyed {
+            report.push_str(", it was destroyed");
+        }
+        report
+    };
+    utils::send_text(world_data, server_data, &outcome.victim_id, victim_report).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell_def::Orientation;
+
+    // Alice's Mouth at (1, 1) next to one of Bob's cells at (2, 1)
+    fn fight(victim_kind: CellKind, mouth_energy: i16) -> WorldData {
+        let mut world_data = WorldData::new(4, 4);
+        let alice = world_data.souls.intern("alice");
+        let bob = world_data.souls.intern("bob");
+        world_data.critter_layer.set((1, 1), Cell::new(alice, CellKind::Mouth, mouth_energy, Orientation::E));
+        world_data.critter_layer.set((2, 1), Cell::new(bob, victim_kind, 500, Orientation::C));
+        world_data
+    }
+
+    #[test]
+    fn armor_absorbs_and_reflects_its_share_of_a_bite() {
+        let b_ps = BPs::new();
+        let mut world_data = fight(CellKind::Armor, 500);
+
+        let outcome = bite(&mut world_data, 1, 1, 2, 1, 100, &b_ps);
+        assert_eq!(outcome.absorbed, b_ps.ArmorAbsorb);
+        assert_eq!(outcome.reflected, b_ps.ArmorReflect);
+        assert_eq!(outcome.damage, 100 - b_ps.ArmorAbsorb - b_ps.ArmorReflect);
+        assert_eq!(outcome.eaten, outcome.damage);
+        assert_eq!(world_data.critter_layer.get((2, 1)).energy, 500 - outcome.damage);
+        assert_eq!(world_data.critter_layer.get((1, 1)).energy, 500 - b_ps.ArmorReflect);
+        assert!(!outcome.victim_destroyed && !outcome.mouth_destroyed);
+    }
+
+    #[test]
+    fn other_cells_take_the_whole_bite() {
+        let b_ps = BPs::new();
+        let mut world_data = fight(CellKind::Tissue, 500);
+
+        let outcome = bite(&mut world_data, 1, 1, 2, 1, 100, &b_ps);
+        assert_eq!((outcome.damage, outcome.absorbed, outcome.reflected), (100, 0, 0));
+        assert_eq!(world_data.critter_layer.get((2, 1)).energy, 400);
+        assert_eq!(world_data.critter_layer.get((1, 1)).energy, 500);
+    }
+
+    #[test]
+    fn reflected_damage_can_destroy_the_mouth() {
+        let b_ps = BPs::new();
+        let mut world_data = fight(CellKind::Armor, 1);
+
+        let outcome = bite(&mut world_data, 1, 1, 2, 1, 100, &b_ps);
+        assert!(outcome.mouth_destroyed);
+        assert!(world_data.critter_layer.is_empty((1, 1)));
+        assert_eq!(world_data.critter_layer.kind((2, 1)), CellKind::Armor);
+    }
+}
@@ -0,0 +1,175 @@
+// This file houses the critter layer. Cells are stored as a struct of arrays, one chunked grid per field, so passes that
+// only look at one field (like kind or energy) walk tightly packed memory and an empty square costs no allocation at all.
+use serde::{Serialize, Deserialize};
+
+use crate::cell_def;
+use crate::chunks::ChunkedGrid;
+use cell_def::{Cell, CellKind, Orientation, SoulHandle};
+
+#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct CritterLayer {
+    owners: ChunkedGrid<SoulHandle>,
+    kinds: ChunkedGrid<CellKind>,
+    energy: ChunkedGrid<i16>,
+    orientations: ChunkedGrid<Orientation>,
+}
+
+impl CritterLayer {
+    pub fn new(width: usize, height: usize) -> Self {
+        let empty = Cell::empty();
+        Self {
+            owners: ChunkedGrid::new(width, height, empty.owner),
+            kinds: ChunkedGrid::new(width, height, empty.kind),
+            energy: ChunkedGrid::new(width, height, empty.energy),
+            orientations: ChunkedGrid::new(width, height, empty.orientation),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.kinds.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.kinds.height()
+    }
+
+    pub fn get(&self, (x, y): (usize, usize)) -> Cell {
+        Cell {
+            owner: *self.owners.get(x, y),
+            kind: *self.kinds.get(x, y),
+            energy: *self.energy.get(x, y),
+            orientation: *self.orientations.get(x, y),
+        }
+    }
+
+    pub fn set(&mut self, (x, y): (usize, usize), cell: Cell) {
+        self.owners.set(x, y, cell.owner);
+        self.kinds.set(x, y, cell.kind);
+        self.energy.set(x, y, cell.energy);
+        self.orientations.set(x, y, cell.orientation);
+    }
+
+    pub fn clear(&mut self, square: (usize, usize)) {
+        self.set(square, Cell::empty());
+    }
+
+    pub fn is_empty(&self, (x, y): (usize, usize)) -> bool {
+        *self.kinds.get(x, y) == CellKind::Empty
+    }
+
+    pub fn kind(&self, (x, y): (usize, usize)) -> CellKind {
+        *self.kinds.get(x, y)
+    }
+
+    pub fn owner(&self, (x, y): (usize, usize)) -> SoulHandle {
+        *self.owners.get(x, y)
+    }
+
+    pub fn energy_mut(&mut self, (x, y): (usize, usize)) -> &mut i16 {
+        self.energy.get_mut(x, y)
+    }
+
+    pub fn set_orientation(&mut self, (x, y): (usize, usize), orientation: Orientation) {
+        self.orientations.set(x, y, orientation);
+    }
+
+    // Every square holding a critter cell. Only the kind of each square is read, so this skips empty squares cheaply.
+    pub fn occupied_squares(&self) -> Vec<(usize, usize)> {
+        self.kinds.allocated_values().filter(|(_, kind)| **kind != CellKind::Empty).map(|(square, _)| square).collect()
+    }
+
+    pub fn allocated_count(&self) -> usize {
+        self.kinds.allocated_count()
+    }
+
+    pub fn free_unused(&mut self) {
+        self.owners.free_unused();
+        self.kinds.free_unused();
+        self.energy.free_unused();
+        self.orientations.free_unused();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Instant;
+    use tokio::sync::Mutex;
+
+    use crate::chunks::{ChunkedGrid, CHUNK_SIZE};
+    use crate::{body, lifecycle, metabolism, BPs, ServerData, WorldData};
+
+    // A cell as the critter layer stored it before cells were split into per field grids, owner's soul id inline. The
+    // orientation was an enum then, which bincode wrote as a u32 variant index.
+    #[derive(Clone, PartialEq, Serialize)]
+    struct StringCell {
+        id: String,
+        kind: CellKind,
+        energy: i16,
+        orientation: u32,
+    }
+
+    #[test]
+    fn cell_fits_in_eight_bytes() {
+        assert_eq!(std::mem::size_of::<SoulHandle>(), 4);
+        assert_eq!(std::mem::size_of::<CellKind>(), 1);
+        assert_eq!(std::mem::size_of::<Orientation>(), 1);
+        assert_eq!(std::mem::size_of::<Cell>(), 8);
+    }
+
+    // Memory, save size and time per tick of the critter passes on a 1000x1000 world holding 9801 critters of 25 cells.
+    // Memory and save size are also measured for the same cells in a single grid of StringCells, the layout this one
+    // replaced. Run with: cargo test --release -- --ignored --nocapture critter_layer_benchmark
+    #[tokio::test]
+    #[ignore]
+    async fn critter_layer_benchmark() {
+        let b_ps = BPs::new();
+        let mut world_data = WorldData::new(1000, 1000);
+        let server_data = Arc::new(Mutex::new(ServerData::new()));
+
+        for cy in (5..995).step_by(10) {
+            for cx in (5..995).step_by(10) {
+                let soul_id = format!("critter{}_{}", cx, cy);
+                let owner = world_data.souls.intern(&soul_id);
+                for dy in -2..=2 {
+                    for dx in -2..=2 {
+                        let kind = if (dx, dy) == (0, 0) { CellKind::Soul } else { CellKind::Tissue };
+                        world_data.critter_layer.set(((cx + dx) as usize, (cy + dy) as usize), Cell::new(owner, kind, 30000, Orientation::C));
+                    }
+                }
+                world_data.soul_locations.push((soul_id, cx as u32, cy as u32));
+            }
+        }
+
+        let square_bytes = std::mem::size_of::<SoulHandle>() + std::mem::size_of::<CellKind>() + std::mem::size_of::<i16>() + std::mem::size_of::<Orientation>();
+        let layer_bytes = world_data.critter_layer.allocated_count() * CHUNK_SIZE * CHUNK_SIZE * square_bytes;
+        let table_bytes = bincode::serialize(&world_data.souls).unwrap().len();
+        println!("{} souls, critter layer ~{} bytes in memory plus ~{} bytes of soul table", world_data.souls.len(), layer_bytes, table_bytes);
+        println!("critter layer saves to {} bytes", bincode::serialize(&world_data.critter_layer).unwrap().len());
+
+        let empty = StringCell { id: "Null".to_string(), kind: CellKind::Empty, energy: 0, orientation: 0 };
+        let mut string_layer = ChunkedGrid::new(1000, 1000, empty);
+        for (x, y) in world_data.critter_layer.occupied_squares() {
+            let cell = world_data.critter_layer.get((x, y));
+            let id = world_data.souls.name(cell.owner).to_string();
+            string_layer.set(x, y, StringCell { id, kind: cell.kind, energy: cell.energy, orientation: 0 });
+        }
+        let string_squares = string_layer.allocated_squares();
+        let string_bytes = string_squares.len() * std::mem::size_of::<StringCell>()
+            + string_squares.iter().map(|&(x, y)| string_layer[(x, y)].id.capacity()).sum::<usize>();
+        println!("single grid of StringCells ~{} bytes in memory, saves to {} bytes", string_bytes, bincode::serialize(&string_layer).unwrap().len());
+
+        let ticks = 5;
+        let start = Instant::now();
+        for _ in 0..ticks {
+            metabolism::flow_energy(&mut world_data, &b_ps);
+            metabolism::metabolize(&mut world_data, &b_ps, &server_data).await;
+            body::orphan_all_detached(&mut world_data, &server_data).await;
+            lifecycle::decay_corpses(&mut world_data, &b_ps);
+            world_data.critter_layer.free_unused();
+        }
+        println!("flow, metabolize, orphan and decay passes take {:?} per tick", start.elapsed() / ticks);
+    }
+}
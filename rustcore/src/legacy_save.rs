@@ -0,0 +1,127 @@
+// This file converts worlds saved before saves were versioned. Back then a save was the bare bincode of a world layer, a
+// critter layer of cells carrying their soul id as a string, and the soul locations, both layers indexed [y][x].
+use serde::Deserialize;
+use bincode::Options;
+
+use crate::cell_def::{Cell, CellKind, Orientation};
+use crate::WorldData;
+
+// Same variants in the same order as CellKind had then, bincode stores the variant index
+#[derive(Deserialize)]
+enum LegacyCellKind {
+    Empty,
+    Soul,
+    Tissue,
+    Eyeball,
+    Mouth,
+    Butt,
+    Muscle,
+    Anchor,
+    Armor,
+}
+
+#[derive(Deserialize)]
+struct LegacyCell {
+    id: String,
+    kind: LegacyCellKind,
+    energy: i16,
+    orientation: String,
+}
+
+#[derive(Deserialize)]
+struct LegacyWorldData {
+    world: Vec<Vec<u8>>,
+    critter_layer: Vec<Vec<LegacyCell>>,
+    soul_locations: Vec<(String, u32, u32)>,
+}
+
+impl LegacyCellKind {
+    fn current(&self) -> CellKind {
+        match self {
+            LegacyCellKind::Empty => CellKind::Empty,
+            LegacyCellKind::Soul => CellKind::Soul,
+            LegacyCellKind::Tissue => CellKind::Tissue,
+            LegacyCellKind::Eyeball => CellKind::Eyeball,
+            LegacyCellKind::Mouth => CellKind::Mouth,
+            LegacyCellKind::Butt => CellKind::Butt,
+            LegacyCellKind::Muscle => CellKind::Muscle,
+            LegacyCellKind::Anchor => CellKind::Anchor,
+            LegacyCellKind::Armor => CellKind::Armor,
+        }
+    }
+}
+
+// None if the bytes are not a whole unversioned save. Those worlds had no terrain, so the converted world is bounded open
+// grassland, and every square can regrow to 255 like the old sun let it.
+pub fn convert(bytes: &[u8]) -> Option<WorldData> {
+    let legacy: LegacyWorldData = bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_limit(bytes.len() as u64)
+        .deserialize(bytes)
+        .ok()?;
+
+    let height = legacy.world.len();
+    let width = legacy.world.first()?.len();
+    if width == 0 || legacy.world.iter().any(|row| row.len() != width) {
+        return None;
+    }
+    if legacy.critter_layer.len() != height || legacy.critter_layer.iter().any(|row| row.len() != width) {
+        return None;
+    }
+
+    let mut world_data = WorldData::new(width, height);
+    for (y, row) in legacy.world.iter().enumerate() {
+        for (x, food) in row.iter().enumerate() {
+            world_data.world[(x, y)] = *food;
+            world_data.capacity[(x, y)] = 255;
+        }
+    }
+    for (y, row) in legacy.critter_layer.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            let kind = cell.kind.current();
+            if kind == CellKind::Empty {
+                continue;
+            }
+            let owner = world_data.souls.intern(&cell.id);
+            let orientation = Orientation::from_input_string(&cell.orientation).unwrap_or(Orientation::C);
+            world_data.critter_layer.set((x, y), Cell::new(owner, kind, cell.energy, orientation));
+        }
+    }
+    world_data.soul_locations = legacy.soul_locations;
+    world_data.tag_key = rand::random();
+    Some(world_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Bincode wrote structs as tuples and enum variants as their u32 index, so old saves can be built from plain tuples
+    #[test]
+    fn converts_cells_and_souls() {
+        let empty = ("Null".to_string(), 0u32, 0i16, "C".to_string());
+        let mut critters = vec![vec![empty.clone(); 3]; 2];
+        critters[1][2] = ("alice".to_string(), 1, 40, "C".to_string()); // Soul
+        critters[0][2] = ("alice".to_string(), 6, 7, "W".to_string()); // Muscle
+        let world = vec![vec![1u8, 2, 3], vec![4, 5, 6]];
+        let locations = vec![("alice".to_string(), 2u32, 1u32)];
+        let bytes = bincode::serialize(&(world, critters, locations)).unwrap();
+
+        let world_data = convert(&bytes).expect("legacy save did not convert");
+        assert_eq!((world_data.width(), world_data.height()), (3, 2));
+        assert_eq!(world_data.world[(0, 1)], 4);
+        let soul = world_data.critter_layer.get((2, 1));
+        assert_eq!((soul.kind, soul.energy), (CellKind::Soul, 40));
+        assert!(world_data.is_owned_by("alice", 2, 0));
+        assert_eq!(world_data.critter_layer.get((2, 0)).orientation, Orientation::W);
+        assert!(world_data.critter_layer.is_empty((0, 0)));
+        assert_eq!(world_data.soul_locations, vec![("alice".to_string(), 2, 1)]);
+    }
+
+    #[test]
+    fn malformed_saves_do_not_convert() {
+        let bytes = bincode::serialize(&(vec![vec![0u8; 2]; 2], Vec::<Vec<u8>>::new(), Vec::<u8>::new())).unwrap();
+        assert!(convert(&bytes).is_none());
+        assert!(convert(&bytes[..bytes.len() - 3]).is_none());
+    }
+}
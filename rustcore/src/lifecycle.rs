@@ -0,0 +1,214 @@
+// This file houses the soul lifecycle. Souls bud offspring, dead souls leave corpses behind, corpses rot back into
+// the world layer, and a dead soul may only be generated again once its respawn cooldown has run out.
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use rand::Rng;
+use uuid::Builder;
+
+use crate::cell_def;
+use crate::utils;
+use crate::body;
+use crate::UserInput;
+use crate::WorldData;
+use crate::BPs;
+use crate::ServerData;
+use cell_def::{Cell, CellKind, Orientation, SoulHandle};
+
+// Removes a soul from the world. Every cell it still owns becomes neutral corpse matter holding the same energy.
+pub fn kill_soul(world_data: &mut WorldData, soul_id: &str, b_ps: &BPs) {
+    let Some(index) = world_data.soul_locations.iter().position(|(s, _, _)| s == soul_id) else {
+        return;
+    };
+
+    let (_, x, y) = world_data.soul_locations.remove(index);
+    world_data.critter_layer.clear((x as usize, y as usize));
+    world_data.guts.remove(soul_id);
+    world_data.anchored.remove(soul_id);
+    world_data.respawn_at.insert(soul_id.to_string(), world_data.tick + b_ps.RespawnCooldown as u64);
+    world_data.brains.remove(soul_id);
+
+    if let Some(owner) = world_data.souls.handle(soul_id) {
+        for square in world_data.critter_layer.occupied_squares() {
+            let cell = world_data.critter_layer.get(square);
+            if !cell.is_empty() && cell.owner == owner {
+                world_data.critter_layer.set(square, corpse(cell.energy));
+            }
+        }
+        world_data.vibrations.retain(|vibration| vibration.owner != owner);
+        world_data.souls.release(soul_id);
+    }
+
+    println!("Soul {} died at ({}, {})", soul_id, x, y);
+}
+
+pub fn corpse(energy: i16) -> Cell {
+    Cell::new(SoulHandle::NONE, CellKind::Corpse, energy, Orientation::C)
+}
+
+// Rots every corpse a little each tick. The lost energy becomes food on the world square under it.
+pub fn decay_corpses(world_data: &mut WorldData, b_ps: &BPs) {
+    for square in world_data.critter_layer.occupied_squares() {
+        if world_data.critter_layer.kind(square) != CellKind::Corpse {
+            continue;
+        }
+
+        let energy = world_data.critter_layer.energy_mut(square);
+        let rotted = b_ps.CorpseDecay.min(*energy).max(0);
+        *energy -= rotted;
+        if *energy <= 0 {
+            world_data.critter_layer.clear(square);
+        }
+
+        let food = &mut world_data.world[square];
+        *food = (*food as i16 + rotted).min(255) as u8;
+    }
+}
+
+// Ticks left before a dead soul may be generated again, 0 if it is free to respawn
+pub fn respawn_cooldown(world_data: &WorldData, soul_id: &str) -> u64 {
+    world_data.respawn_at.get(soul_id).map_or(0, |tick| tick.saturating_sub(world_data.tick))
+}
+
+// Soul that budded an offspring, None for souls a player generated
+pub fn parent_of<'a>(world_data: &'a WorldData, soul_id: &str) -> Option<&'a str> {
+    world_data.parents.get(soul_id).map(String::as_str)
+}
+
+// First soul of the lineage, the one with a session of its own
+pub fn root_of<'a>(world_data: &'a WorldData, soul_id: &'a str) -> &'a str {
+    let mut root = soul_id;
+    while let Some(parent) = parent_of(world_data, root) {
+        root = parent;
+    }
+    root
+}
+
+pub fn is_descendant_of(world_data: &WorldData, soul_id: &str, ancestor: &str) -> bool {
+    let mut soul = soul_id;
+    while let Some(parent) = parent_of(world_data, soul) {
+        if parent == ancestor {
+            return true;
+        }
+        soul = parent;
+    }
+    false
+}
+
+// Buds an offspring soul next to the parent's body. The offspring copies the parent's cells near its Soul and its brain
+// code, and the parent pays for all of it: a base cost, the energy handed to the offspring and every copied cell.
+pub async fn reproduce(world_data: &mut WorldData, reproduce_que: &[UserInput], b_ps: &BPs, server_data: &Arc<Mutex<ServerData>>) {
+    for input in reproduce_que.iter() {
+        let UserInput::Reproduce { soul_id, X, Y, energy } = input else {
+            continue;
+        };
+
+        if *energy <= 0 {
+            utils::send_text(world_data, server_data, soul_id, "Offspring need some energy to be born with".to_string()).await;
+            continue;
+        }
+
+        let Some(&(_, parent_x, parent_y)) = world_data.soul_locations.iter().find(|(s, _, _)| s == soul_id) else {
+            println!("Soul {} cannot reproduce, it has no location", soul_id);
+            continue;
+        };
+        let (parent_x, parent_y) = (parent_x as i32, parent_y as i32);
+
+        if !world_data.is_passable(*X, *Y) || !world_data.critter_layer.is_empty((*X as usize, *Y as usize)) {
+            utils::send_text(world_data, server_data, soul_id, format!("Cannot bud an offspring at ({}, {}), the square is taken", X, Y)).await;
+            continue;
+        }
+
+        let parent_body = body::connected_cells(world_data, soul_id, parent_x as usize, parent_y as usize);
+        if !body::touches(world_data, &parent_body, *X, *Y) {
+            utils::send_text(world_data, server_data, soul_id, "Offspring must be budded next to the parent's body".to_string()).await;
+            continue;
+        }
+
+        // Copy the body plan closest to the parent's Soul first, each copied cell has to attach to what is already placed
+        let mut body_plan: Vec<(i32, i32)> = parent_body.iter()
+            .map(|&(bx, by)| world_data.offset_between((parent_x, parent_y), (bx as i32, by as i32)))
+            .filter(|(dx, dy)| (*dx, *dy) != (0, 0) && dx.abs().max(dy.abs()) <= b_ps.BudRadius as i32)
+            .collect();
+        body_plan.sort_by_key(|(dx, dy)| (dx.abs().max(dy.abs()), *dy, *dx));
+
+        let mut placed: HashSet<(usize, usize)> = HashSet::from([(*X as usize, *Y as usize)]);
+        let mut copies: Vec<((usize, usize), Cell)> = Vec::new();
+        let mut cost = b_ps.ReproduceCost as i32 + *energy as i32;
+
+        for (dx, dy) in body_plan {
+            let Some((cx, cy)) = world_data.resolve(*X + dx, *Y + dy) else {
+                continue;
+            };
+            if !world_data.is_passable(cx as i32, cy as i32) || !world_data.critter_layer.is_empty((cx, cy)) || !body::touches(world_data, &placed, cx as i32, cy as i32) {
+                continue;
+            }
+
+            let Some((tx, ty)) = world_data.resolve(parent_x + dx, parent_y + dy) else {
+                continue;
+            };
+            let template = world_data.critter_layer.get((tx, ty));
+            let Some(base_cost) = b_ps.BuildCost.get(&template.kind) else {
+                continue;
+            };
+
+            cost += *base_cost as i32 + b_ps.BudCellEnergy as i32;
+            placed.insert((cx, cy));
+            copies.push(((cx, cy), Cell::new(SoulHandle::NONE, template.kind, b_ps.BudCellEnergy, template.orientation)));
+        }
+
+        if cost > i16::MAX as i32 || !utils::draw_energy(world_data, soul_id, cost as i16) {
+            utils::send_text(world_data, server_data, soul_id, format!("Cannot afford to reproduce, it costs {} Energy", cost)).await;
+            continue;
+        }
+
+        // Offspring ids give nothing away about the parent, the lineage is kept in world_data.parents
+        let offspring_id = Builder::from_random_bytes(world_data.rng.random()).into_uuid().to_string();
+        let offspring = world_data.souls.intern(&offspring_id);
+
+        world_data.critter_layer.set((*X as usize, *Y as usize), Cell::new(offspring, CellKind::Soul, *energy, Orientation::C));
+        for ((cx, cy), mut cell) in copies {
+            cell.owner = offspring;
+            world_data.critter_layer.set((cx, cy), cell);
+        }
+        world_data.soul_locations.push((offspring_id.clone(), *X as u32, *Y as u32));
+        world_data.parents.insert(offspring_id.clone(), soul_id.clone());
+        if let Some(code) = world_data.brains.get(soul_id).cloned() {
+            world_data.brains.insert(offspring_id.clone(), code);
+        }
+
+        println!("Soul {} budded offspring {} at ({}, {})", soul_id, offspring_id, X, Y);
+        utils::send_text(world_data, server_data, soul_id, format!("Offspring {} was born", offspring_id)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A whitelisted soul id may contain '/', lineage only ever comes from world_data.parents
+    #[tokio::test]
+    async fn offspring_get_opaque_ids_and_an_explicit_parent() {
+        let b_ps = BPs::new();
+        let server_data = Arc::new(Mutex::new(ServerData::new()));
+        let mut world_data = WorldData::new(10, 10);
+        let parent_id = "team/alice".to_string();
+        let owner = world_data.souls.intern(&parent_id);
+        world_data.critter_layer.set((4, 4), Cell::new(owner, CellKind::Soul, 5000, Orientation::C));
+        world_data.soul_locations.push((parent_id.clone(), 4, 4));
+        assert_eq!(parent_of(&world_data, &parent_id), None);
+
+        let bud = UserInput::Reproduce { soul_id: parent_id.clone(), X: 5, Y: 4, energy: 300 };
+        reproduce(&mut world_data, &[bud], &b_ps, &server_data).await;
+        let child_id = world_data.parents.keys().next().expect("no offspring was born").clone();
+        assert!(!child_id.contains("alice"));
+        assert!(world_data.is_owned_by(&child_id, 5, 4));
+
+        world_data.parents.insert("grandchild".to_string(), child_id.clone());
+        assert_eq!(parent_of(&world_data, "grandchild"), Some(child_id.as_str()));
+        assert_eq!(root_of(&world_data, "grandchild"), "team/alice");
+        assert!(is_descendant_of(&world_data, "grandchild", "team/alice"));
+        assert!(!is_descendant_of(&world_data, "team/alice", "team"));
+        assert!(!is_descendant_of(&world_data, &child_id, &child_id));
+    }
+}
@@ -0,0 +1,229 @@
+// This file houses the per tick energy passes. Energy flows through Tissue networks, every living cell pays upkeep,
+// and cells that run dry die.
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::cell_def;
+use crate::utils;
+use crate::lifecycle;
+use crate::WorldData;
+use crate::BPs;
+use crate::ServerData;
+use crate::chunks::ChunkedGrid;
+use cell_def::CellKind;
+
+// Charges every cell its upkeep for this tick. Starved cells are cleared, and a starved Soul kills its soul.
+pub async fn metabolize(world_data: &mut WorldData, b_ps: &BPs, server_data: &Arc<Mutex<ServerData>>) {
+    let mut dead_souls: Vec<String> = Vec::new();
+
+    for square in world_data.critter_layer.occupied_squares() {
+        let kind = world_data.critter_layer.kind(square);
+        if kind == CellKind::Empty {
+            continue;
+        }
+
+        let upkeep = b_ps.Upkeep.get(&kind).copied().unwrap_or(0);
+        let energy = world_data.critter_layer.energy_mut(square);
+        *energy = energy.saturating_sub(upkeep);
+
+        if *energy <= 0 {
+            if kind == CellKind::Soul {
+                dead_souls.push(world_data.souls.name(world_data.critter_layer.owner(square)).to_string());
+            }
+            world_data.critter_layer.clear(square);
+        }
+    }
+
+    for soul_id in dead_souls {
+        lifecycle::kill_soul(world_data, &soul_id, b_ps);
+        utils::send_text(world_data, server_data, &soul_id, "Your soul has starved to death".to_string()).await;
+    }
+}
+
+// Diffuses energy between neighbouring cells of the same soul. Soul and Tissue cells are conduits, other organs only
+// exchange energy with a conduit next to them. A share of every transfer is lost on the way.
+pub fn flow_energy(world_data: &mut WorldData, b_ps: &BPs) {
+    let critter_layer = &world_data.critter_layer;
+    let occupied = critter_layer.occupied_squares();
+    let mut delta = ChunkedGrid::new(critter_layer.width(), critter_layer.height(), 0i32);
+
+    for &(x, y) in &occupied {
+        let cell = critter_layer.get((x, y));
+        if cell.is_empty() {
+            continue;
+        }
+
+        // Only look forward so every pair of neighbours is handled once
+        for (dx, dy) in [(1, 0), (-1, 1), (0, 1), (1, 1)] {
+            let Some((nx, ny)) = world_data.resolve(x as i32 + dx, y as i32 + dy) else {
+                continue;
+            };
+            let other = critter_layer.get((nx, ny));
+            if other.is_empty() || other.owner != cell.owner || !(is_conduit(cell.kind) || is_conduit(other.kind)) {
+                continue;
+            }
+
+            // Conductance is split over the 8 possible links of a cell so a tick can never overshoot
+            let flow = (cell.energy as i32 - other.energy as i32) * b_ps.EnergyConductance as i32 / 800;
+            let (from, to, amount) = if flow > 0 {
+                ((x, y), (nx, ny), flow)
+            } else {
+                ((nx, ny), (x, y), -flow)
+            };
+
+            delta[from] -= amount;
+            delta[to] += amount - amount * b_ps.EnergyFlowLoss as i32 / 100;
+        }
+    }
+
+    for square in occupied {
+        let change = delta[square];
+        if change != 0 {
+            let energy = world_data.critter_layer.energy_mut(square);
+            *energy = (*energy as i32 + change).clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        }
+    }
+}
+
+fn is_conduit(kind: CellKind) -> bool {
+    kind == CellKind::Soul || kind == CellKind::Tissue
+}
+
+// Lets each engaged Anchor siphon food from the world square under it and releases the Anchors whose time is up. An
+// activation on tick T that bought N ticks harvests on ticks T to T + N - 1 and holds the critter through tick T + N, so
+// Muscles are blocked on each of the N ticks after it. A soul that has lost all of its Anchor cells comes loose straight away.
+pub fn tick_anchors(world_data: &mut WorldData, b_ps: &BPs) {
+    let tick = world_data.tick;
+    let mut holding: HashSet<String> = HashSet::new();
+
+    for square in world_data.critter_layer.occupied_squares() {
+        if world_data.critter_layer.kind(square) != CellKind::Anchor {
+            continue;
+        }
+        let soul_id = world_data.souls.name(world_data.critter_layer.owner(square));
+        let Some(&release) = world_data.anchored.get(soul_id) else {
+            continue;
+        };
+        holding.insert(soul_id.to_string());
+        if release <= tick {
+            continue;
+        }
+
+        let food = &mut world_data.world[square];
+        let harvested = b_ps.AnchorHarvest.min(*food as i16).max(0);
+        *food -= harvested as u8;
+        let energy = world_data.critter_layer.energy_mut(square);
+        *energy = energy.saturating_add(harvested);
+    }
+
+    world_data.anchored.retain(|soul_id, release| *release > tick && holding.contains(soul_id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    use crate::cell_def::{Cell, Orientation};
+    use crate::{utils, ServerData, UserInput};
+
+    fn activate(x: i32, power: i16) -> UserInput {
+        UserInput::Activate { soul_id: "alice".to_string(), delay: 0, X: x, Y: 0, power }
+    }
+
+    // An Anchor bought for a single tick has to block the Muscle on the tick after it, and no longer
+    #[tokio::test]
+    async fn one_tick_anchor_blocks_exactly_the_next_tick() {
+        let b_ps = BPs::new();
+        let server_data = Arc::new(Mutex::new(ServerData::new()));
+        let mut world_data = WorldData::new(20, 20);
+        let owner = world_data.souls.intern("alice");
+        world_data.critter_layer.set((5, 5), Cell::new(owner, CellKind::Soul, 1000, Orientation::C));
+        world_data.critter_layer.set((6, 5), Cell::new(owner, CellKind::Anchor, 100, Orientation::C));
+        world_data.critter_layer.set((4, 5), Cell::new(owner, CellKind::Muscle, 1000, Orientation::W)); // Pushes east
+        world_data.soul_locations.push(("alice".to_string(), 5, 5));
+
+        world_data.tick = 1;
+        utils::do_actions(&mut world_data, &vec![activate(1, b_ps.C_AEtoAnchorTick)], &b_ps, &server_data).await;
+        tick_anchors(&mut world_data, &b_ps);
+
+        world_data.tick = 2;
+        utils::do_actions(&mut world_data, &vec![activate(-1, 500)], &b_ps, &server_data).await;
+        tick_anchors(&mut world_data, &b_ps);
+        assert_eq!(world_data.soul_locations[0], ("alice".to_string(), 5, 5));
+        assert!(world_data.anchored.is_empty());
+
+        world_data.tick = 3;
+        utils::do_actions(&mut world_data, &vec![activate(-1, 500)], &b_ps, &server_data).await;
+        assert_ne!(world_data.soul_locations[0], ("alice".to_string(), 5, 5));
+    }
+
+    fn total_energy(world_data: &WorldData) -> i32 {
+        world_data.critter_layer.occupied_squares().into_iter().map(|square| world_data.critter_layer.get(square).energy as i32).sum()
+    }
+
+    // A full Soul ringed by empty cells on all 8 links must never hand out so much that a neighbour ends up above it
+    #[test]
+    fn flow_never_overshoots_or_creates_energy() {
+        let b_ps = BPs::new();
+        let mut world_data = WorldData::new(10, 10);
+        let owner = world_data.souls.intern("alice");
+        world_data.critter_layer.set((5, 5), Cell::new(owner, CellKind::Soul, i16::MAX, Orientation::C));
+        for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+            let square = ((5 + dx) as usize, (5 + dy) as usize);
+            world_data.critter_layer.set(square, Cell::new(owner, CellKind::Tissue, 0, Orientation::C));
+        }
+
+        let mut total = total_energy(&world_data);
+        for _ in 0..50 {
+            flow_energy(&mut world_data, &b_ps);
+            let center = world_data.critter_layer.get((5, 5)).energy;
+            for square in world_data.critter_layer.occupied_squares() {
+                let energy = world_data.critter_layer.get(square).energy;
+                assert!((0..=center).contains(&energy), "{:?} holds {} next to a center of {}", square, energy, center);
+            }
+            let new_total = total_energy(&world_data);
+            assert!(new_total <= total);
+            total = new_total;
+        }
+        assert!(world_data.critter_layer.get((4, 4)).energy > 0);
+    }
+
+    #[test]
+    fn energy_only_flows_within_a_soul_through_conduits() {
+        let b_ps = BPs::new();
+        let mut world_data = WorldData::new(10, 10);
+        let alice = world_data.souls.intern("alice");
+        let bob = world_data.souls.intern("bob");
+        world_data.critter_layer.set((1, 1), Cell::new(alice, CellKind::Soul, 1000, Orientation::C));
+        world_data.critter_layer.set((2, 1), Cell::new(bob, CellKind::Tissue, 0, Orientation::C));
+        world_data.critter_layer.set((5, 5), Cell::new(alice, CellKind::Muscle, 1000, Orientation::C));
+        world_data.critter_layer.set((6, 5), Cell::new(alice, CellKind::Eyeball, 0, Orientation::C));
+
+        flow_energy(&mut world_data, &b_ps);
+        assert_eq!(world_data.critter_layer.get((2, 1)).energy, 0);
+        assert_eq!(world_data.critter_layer.get((6, 5)).energy, 0);
+        assert_eq!(world_data.critter_layer.get((5, 5)).energy, 1000);
+    }
+
+    #[test]
+    fn anchors_harvest_once_per_tick_bought() {
+        let b_ps = BPs::new();
+        let mut world_data = WorldData::new(4, 4);
+        let owner = world_data.souls.intern("alice");
+        world_data.critter_layer.set((1, 1), Cell::new(owner, CellKind::Anchor, 0, Orientation::C));
+        world_data.world[(1, 1)] = 100;
+        world_data.tick = 7;
+        world_data.anchored.insert("alice".to_string(), 7 + 3);
+
+        for tick in 7..=12 {
+            world_data.tick = tick;
+            tick_anchors(&mut world_data, &b_ps);
+        }
+        assert_eq!(world_data.critter_layer.get((1, 1)).energy, 3 * b_ps.AnchorHarvest);
+        assert_eq!(world_data.world[(1, 1)] as i16, 100 - 3 * b_ps.AnchorHarvest);
+        assert!(world_data.anchored.is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+// This file houses the movement subsystem. Activating a Muscle pushes the whole connected critter across the grid.
+use std::collections::HashSet;
+
+use crate::cell_def;
+use crate::body;
+use crate::senses;
+use crate::WorldData;
+use crate::BPs;
+use cell_def::{Cell, CellKind};
+
+// Pushes the critter owning the muscle at (x, y) opposite to the muscle's orientation. Returns a report for the client.
+pub fn muscle_push(world_data: &mut WorldData, soul_id: &String, x: i32, y: i32, power: i16, b_ps: &BPs) -> String {
+    let Some(&(_, soul_x, soul_y)) = world_data.soul_locations.iter().find(|(s, _, _)| s == soul_id) else {
+        return format!("Soul {} has no location", soul_id);
+    };
+
+    if is_anchored(world_data, soul_id) {
+        return "This critter is anchored in place".to_string();
+    }
+
+    let mut body = body::connected_cells(world_data, soul_id, soul_x as usize, soul_y as usize);
+    if !body.contains(&(x as usize, y as usize)) {
+        return "This Muscle is not connected to its soul".to_string();
+    }
+
+    // The muscle pushes off the square it faces, so the critter moves the other way
+    let (dx, dy) = world_data.critter_layer.get((x as usize, y as usize)).orientation.offset();
+    if (dx, dy) == (0, 0) {
+        return "A centered Muscle has nothing to push against".to_string();
+    }
+    let (dx, dy) = (-dx, -dy);
+    senses::record_vibration(world_data, soul_id, x, y, power);
+
+    // Every Anchor in the critter soaks up some of the push before it moves
+    let anchors = body.iter()
+        .filter(|(bx, by)| world_data.critter_layer.kind((*bx, *by)) == CellKind::Anchor)
+        .count() as i16;
+    let effective_power = power.saturating_sub(anchors.saturating_mul(b_ps.AnchorResistance));
+    let steps = (effective_power / b_ps.C_AEtoStep).clamp(0, b_ps.MaxMuscleSteps);
+
+    let mut moved = 0;
+    for _ in 0..steps {
+        match step_body(world_data, &body, dx, dy) {
+            Some(new_body) => body = new_body,
+            None => break,
+        }
+        moved += 1;
+    }
+
+    // Keep the soul location in sync so local coordinates still point at the same cells
+    let (new_x, new_y) = world_data.wrap_point(soul_x as i32 + dx * moved, soul_y as i32 + dy * moved);
+    if let Some(location) = world_data.soul_locations.iter_mut().find(|(s, _, _)| s == soul_id) {
+        location.1 = new_x as u32;
+        location.2 = new_y as u32;
+    }
+
+    if moved < steps as i32 {
+        format!("Muscle moved the critter {} of {} squares before it was blocked", moved, steps)
+    } else {
+        format!("Muscle moved the critter {} squares", moved)
+    }
+}
+
+// Moves every cell in `body` one square by (dx, dy). Returns the new body, or None if the edge, impassable ground or another critter is in the way.
+fn step_body(world_data: &mut WorldData, body: &HashSet<(usize, usize)>, dx: i32, dy: i32) -> Option<HashSet<(usize, usize)>> {
+    let mut new_body = HashSet::with_capacity(body.len());
+
+    let mut moves = Vec::with_capacity(body.len());
+
+    for &(bx, by) in body {
+        let Some(destination) = world_data.resolve(bx as i32 + dx, by as i32 + dy) else {
+            return None;
+        };
+        if !world_data.is_passable(destination.0 as i32, destination.1 as i32) {
+            return None;
+        }
+
+        if !body.contains(&destination) && !world_data.critter_layer.is_empty(destination) {
+            return None;
+        }
+        new_body.insert(destination);
+        moves.push(((bx, by), destination));
+    }
+
+    // Lift the whole body off the grid first so cells never overwrite each other mid move
+    let lifted: Vec<((usize, usize), Cell)> = moves.iter()
+        .map(|&(from, destination)| {
+            let cell = world_data.critter_layer.get(from);
+            world_data.critter_layer.clear(from);
+            (destination, cell)
+        })
+        .collect();
+
+    for (destination, cell) in lifted {
+        world_data.critter_layer.set(destination, cell);
+    }
+
+    Some(new_body)
+}
+
+// True while the soul has engaged Anchors, anchored critters cannot be moved by anything
+pub fn is_anchored(world_data: &WorldData, soul_id: &str) -> bool {
+    world_data.anchored.get(soul_id).is_some_and(|release| world_data.tick <= *release)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell_def::Orientation;
+    use crate::worldgen::Terrain;
+
+    // A soul with a west facing Muscle behind it, so activating the Muscle pushes the critter east
+    fn place_critter(world_data: &mut WorldData, x: usize, y: usize) {
+        let owner = world_data.souls.intern("alice");
+        world_data.critter_layer.set((x, y), Cell::new(owner, CellKind::Soul, 1000, Orientation::C));
+        world_data.critter_layer.set((x - 1, y), Cell::new(owner, CellKind::Muscle, 1000, Orientation::W));
+        world_data.soul_locations.push(("alice".to_string(), x as u32, y as u32));
+    }
+
+    #[test]
+    fn rock_stops_the_push_part_way() {
+        let b_ps = BPs::new();
+        let mut world_data = WorldData::new(20, 20);
+        place_critter(&mut world_data, 5, 5);
+        world_data.terrain[(7, 5)] = Terrain::Rock;
+
+        let report = muscle_push(&mut world_data, &"alice".to_string(), 4, 5, 3 * b_ps.C_AEtoStep, &b_ps);
+        assert_eq!(report, "Muscle moved the critter 1 of 3 squares before it was blocked");
+        assert_eq!(world_data.soul_locations[0], ("alice".to_string(), 6, 5));
+        assert_eq!(world_data.critter_layer.kind((6, 5)), CellKind::Soul);
+        assert_eq!(world_data.critter_layer.kind((5, 5)), CellKind::Muscle);
+        assert!(world_data.critter_layer.is_empty((4, 5)));
+    }
+
+    #[test]
+    fn pushes_wrap_across_the_edge_only_on_a_wrapping_world() {
+        let b_ps = BPs::new();
+        let alice = "alice".to_string();
+
+        let mut bounded = WorldData::new(10, 10);
+        place_critter(&mut bounded, 9, 5);
+        let report = muscle_push(&mut bounded, &alice, 8, 5, b_ps.C_AEtoStep, &b_ps);
+        assert_eq!(report, "Muscle moved the critter 0 of 1 squares before it was blocked");
+        assert_eq!(bounded.soul_locations[0], (alice.clone(), 9, 5));
+
+        let mut wrapping = WorldData::new(10, 10);
+        wrapping.wrap = true;
+        place_critter(&mut wrapping, 9, 5);
+        let report = muscle_push(&mut wrapping, &alice, 8, 5, b_ps.C_AEtoStep, &b_ps);
+        assert_eq!(report, "Muscle moved the critter 1 squares");
+        assert_eq!(wrapping.soul_locations[0], (alice, 0, 5));
+        assert_eq!(wrapping.critter_layer.kind((0, 5)), CellKind::Soul);
+        assert_eq!(wrapping.critter_layer.kind((9, 5)), CellKind::Muscle);
+        assert!(wrapping.critter_layer.is_empty((8, 5)));
+    }
+}
@@ -0,0 +1,211 @@
+// This file houses the resource regrowth models that refill the world layer every tick.
+use serde::Deserialize;
+use std::f32::consts::PI;
+use std::collections::HashSet;
+
+use crate::WorldData;
+use crate::BPs;
+use crate::worldgen::Terrain;
+use crate::chunks::ChunkedGrid;
+
+// Which regrowth model the world runs, picked with RegrowthModel in config.toml
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegrowthModel {
+    Uniform, // Every square grows by 1 per tick until it is full
+    Logistic, // Food grows logistically toward each square's carrying capacity and diffuses into its neighbours
+}
+
+pub fn regrow(world_data: &mut WorldData, b_ps: &BPs) {
+    match b_ps.RegrowthModel {
+        RegrowthModel::Uniform => the_sun(world_data, b_ps),
+        RegrowthModel::Logistic => logistic_regrowth(world_data, b_ps),
+    }
+}
+
+// Grows every square by 1, except on terrain where nothing grows at all. Only dirty chunks are visited, a chunk rests once
+// every square in it is full until something eats from it again.
+pub fn the_sun(world_data: &mut WorldData, b_ps: &BPs) {
+    for chunk in world_data.world.dirty_chunks() {
+        let mut growing = false;
+
+        for square in world_data.world.chunk_squares(chunk) {
+            if world_data.world[square] < 255 && terrain_growth(world_data.terrain[square], b_ps) > 0.0 {
+                world_data.world[square] += 1;
+                growing |= world_data.world[square] < 255;
+            }
+        }
+
+        if !growing {
+            world_data.world.mark_clean(chunk);
+        }
+    }
+}
+
+// How much more food a square can take before it is at capacity
+fn room(world_data: &WorldData, x: usize, y: usize) -> f32 {
+    (world_data.capacity[(x, y)] as f32 - world_data.world[(x, y)] as f32).max(0.0)
+}
+
+// Multiplier on the growth rate from the ground a square is covered with
+fn terrain_growth(terrain: Terrain, b_ps: &BPs) -> f32 {
+    b_ps.TerrainGrowth.get(&terrain).copied().unwrap_or(100) as f32 / 100.0
+}
+
+// Multiplier on the growth rate from the time of year and time of day, never below 0
+pub fn seasonal_factor(tick: u64, b_ps: &BPs) -> f32 {
+    let cycle = |length: i16, amplitude: i16| {
+        if length <= 0 {
+            return 0.0;
+        }
+        let phase = (tick % length as u64) as f32 / length as f32;
+        amplitude as f32 / 100.0 * (2.0 * PI * phase).sin()
+    };
+
+    (1.0 + cycle(b_ps.SeasonLength, b_ps.SeasonAmplitude) + cycle(b_ps.DayLength, b_ps.DayAmplitude)).max(0.0)
+}
+
+// Only dirty chunks are simulated. A chunk rests once none of its squares changed in a tick, and wakes up again when
+// something eats from it or food diffuses into it from a neighbouring chunk. Without a seed a square below capacity
+// might stop changing only because growth is too slow to show, so then a chunk also has to be full to rest.
+fn logistic_regrowth(world_data: &mut WorldData, b_ps: &BPs) {
+    let active = world_data.world.dirty_chunks();
+    let active_set: HashSet<usize> = active.iter().copied().collect();
+    let mut change = ChunkedGrid::new(world_data.width(), world_data.height(), 0.0f32);
+
+    // Diffusion, every pair of orthogonal neighbours evens out a share of their difference. Food does not spread onto rock or water.
+    let diffusion = b_ps.FoodDiffusion as f32 / 100.0 / 4.0;
+    for &chunk in &active {
+        for (x, y) in world_data.world.chunk_squares(chunk) {
+            for (dx, dy) in [(1, 0), (0, 1), (-1, 0), (0, -1)] {
+                let Some((nx, ny)) = world_data.resolve(x as i32 + dx, y as i32 + dy) else {
+                    continue;
+                };
+                // Pairs with both squares in active chunks are handled from one side only, pairs reaching into a
+                // resting chunk are always handled from the active side
+                if (dx < 0 || dy < 0) && active_set.contains(&world_data.world.chunk_of(nx, ny)) {
+                    continue;
+                }
+                if !world_data.terrain[(x, y)].is_passable() || !world_data.terrain[(nx, ny)].is_passable() {
+                    continue;
+                }
+                // Food only spreads into squares with room for it, so a fully grown area settles down
+                let flow = (world_data.world[(x, y)] as f32 - world_data.world[(nx, ny)] as f32) * diffusion;
+                let flow = if flow > 0.0 {
+                    flow.min(room(world_data, nx, ny))
+                } else {
+                    flow.max(-room(world_data, x, y))
+                };
+                if flow == 0.0 {
+                    continue;
+                }
+                change[(x, y)] -= flow;
+                change[(nx, ny)] += flow;
+            }
+        }
+    }
+
+    // Logistic growth toward the carrying capacity, seeded so bare squares can recover
+    let rate = b_ps.RegrowthRate as f32 / 100.0 * seasonal_factor(world_data.tick, b_ps);
+    for &chunk in &active {
+        let mut growing = false;
+
+        for square in world_data.world.chunk_squares(chunk) {
+            let capacity = world_data.capacity[square] as f32;
+            let growth = terrain_growth(world_data.terrain[square], b_ps);
+            let old = world_data.world[square];
+            let mut f = old as f32 + change[square];
+            if capacity > 0.0 && growth > 0.0 {
+                f += rate * growth * f * (1.0 - f / capacity);
+                if f < capacity {
+                    f = (f + b_ps.RegrowthSeed as f32).min(capacity);
+                }
+            }
+
+            let new = f.round().clamp(0.0, 255.0) as u8;
+            if new != old {
+                world_data.world[square] = new;
+                growing = true;
+            }
+            growing |= b_ps.RegrowthSeed <= 0 && growth > 0.0 && (new as f32) < capacity;
+        }
+
+        if !growing {
+            world_data.world.mark_clean(chunk);
+        }
+    }
+
+    // Food that diffused into resting chunks, writing it wakes them up
+    for chunk in change.allocated_chunks() {
+        if active_set.contains(&chunk) {
+            continue;
+        }
+        for square in change.chunk_squares(chunk) {
+            let old = world_data.world[square];
+            let new = (old as f32 + change[square]).round().clamp(0.0, 255.0) as u8;
+            if new != old {
+                world_data.world[square] = new;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Open ground with the same capacity and the same amount of food on every square, every chunk allocated and dirty
+    fn uniform_world(width: usize, height: usize, capacity: u8, food: u8) -> WorldData {
+        let mut world_data = WorldData::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                world_data.capacity[(x, y)] = capacity;
+                world_data.world[(x, y)] = food;
+            }
+        }
+        world_data
+    }
+
+    #[test]
+    fn logistic_regrowth_fills_bare_ground_up_to_capacity() {
+        let b_ps = BPs::new();
+        let mut world_data = uniform_world(64, 64, 200, 0);
+        world_data.world[(30, 30)] = 150;
+
+        for tick in 0..5000 {
+            world_data.tick = tick;
+            logistic_regrowth(&mut world_data, &b_ps);
+            assert!(world_data.world.allocated_values().all(|(square, &food)| food <= world_data.capacity[square]));
+            if world_data.world.dirty_chunks().is_empty() {
+                break;
+            }
+        }
+        assert!(world_data.world.dirty_chunks().is_empty(), "regrowth never settled");
+        assert!(world_data.world.allocated_values().all(|(_, &food)| food == 200));
+    }
+
+    #[test]
+    fn full_chunks_rest_until_something_eats_from_them() {
+        let b_ps = BPs::new();
+        let mut world_data = uniform_world(128, 64, 100, 100);
+
+        for tick in 0..10 {
+            world_data.tick = tick;
+            logistic_regrowth(&mut world_data, &b_ps);
+            assert!(world_data.world.dirty_chunks().is_empty());
+        }
+        assert!(world_data.world.allocated_values().all(|(_, &food)| food == 100));
+
+        world_data.world[(10, 10)] = 50;
+        assert_eq!(world_data.world.dirty_chunks(), vec![world_data.world.chunk_of(10, 10)]);
+        for tick in 10..1000 {
+            world_data.tick = tick;
+            logistic_regrowth(&mut world_data, &b_ps);
+            assert!(!world_data.world.dirty_chunks().contains(&world_data.world.chunk_of(100, 10)));
+            if world_data.world.dirty_chunks().is_empty() {
+                break;
+            }
+        }
+        assert!(world_data.world.dirty_chunks().is_empty());
+        assert_eq!(world_data.world[(10, 10)], 100);
+    }
+}
@@ -0,0 +1,268 @@
+// This file houses the senses other than sight. A Nose smells which way the food is, a Whisker feels foreign cells
+// touching the critter, and an Ear hears nearby Muscles at work. Each sense builds a package that is sent to the client.
+use serde::{Serialize, Deserialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tungstenite::protocol::Message;
+
+use crate::cell_def;
+use crate::body;
+use crate::visual_pkg_generator;
+use visual_pkg_generator::Envelope;
+use crate::WorldData;
+use crate::BPs;
+use crate::ServerData;
+use cell_def::{CellKind, SoulHandle};
+
+// A Muscle push that can still be heard, kept in WorldData for VibrationTicks ticks
+#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct Vibration {
+    pub x: i32,
+    pub y: i32,
+    pub owner: SoulHandle, // Ears never hear their own critter's Muscles
+    pub strength: i16, // Activation energy of the push
+    pub tick: u64,
+}
+
+// What a Nose smelled. The gradient points uphill in food per square, in local coordinates, so +y is north.
+#[derive(Debug, Serialize, Clone)]
+pub struct Smell {
+    pub radius: i32,
+    pub concentration: u8, // Average food on the squares smelled
+    pub gradient_x: f32,
+    pub gradient_y: f32,
+    pub bearing: Option<u16>, // Degrees clockwise from north the food gets richer in, None on even ground
+}
+
+// A foreign cell touching the critter, in local coordinates. Owners are tagged the same way an Eyeball sees them.
+#[derive(Debug, Serialize, Clone)]
+pub struct Contact {
+    pub x: i32,
+    pub y: i32,
+    pub kind: CellKind,
+    pub owner: Option<String>,
+}
+
+// A Muscle push an Ear picked up. Strength fades with distance.
+#[derive(Debug, Serialize, Clone)]
+pub struct Sound {
+    pub bearing: u16, // Degrees clockwise from north
+    pub distance: i32,
+    pub strength: i16,
+    pub ticks_ago: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub enum SensePkg {
+    Smell(Smell),
+    Touch(Vec<Contact>),
+    Vibration(Vec<Sound>),
+}
+
+// Smells the food on every square within the radius the power buys. The gradient is the least squares slope of the food
+// around the Nose.
+pub fn smell(world_data: &WorldData, x: i32, y: i32, power: i16, b_ps: &BPs) -> Smell {
+    let radius = (power / b_ps.C_AEtoSmell.max(1)).max(1) as i32;
+
+    let (mut total, mut count) = (0u32, 0u32);
+    let (mut slope_x, mut slope_y) = (0.0f32, 0.0f32);
+    let (mut spread_x, mut spread_y) = (0.0f32, 0.0f32);
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let Some(square) = world_data.resolve(x + dx, y + dy) else {
+                continue;
+            };
+            if dx * dx + dy * dy > radius * radius || !world_data.terrain[square].is_passable() {
+                continue;
+            }
+
+            let food = world_data.world[square] as f32;
+            total += world_data.world[square] as u32;
+            count += 1;
+            slope_x += food * dx as f32;
+            slope_y += food * -dy as f32; // Global y grows southwards
+            spread_x += (dx * dx) as f32;
+            spread_y += (dy * dy) as f32;
+        }
+    }
+
+    let gradient_x = if spread_x > 0.0 { slope_x / spread_x } else { 0.0 };
+    let gradient_y = if spread_y > 0.0 { slope_y / spread_y } else { 0.0 };
+    Smell {
+        radius,
+        concentration: total.checked_div(count).unwrap_or(0) as u8,
+        gradient_x,
+        gradient_y,
+        bearing: bearing_of(gradient_x, gradient_y),
+    }
+}
+
+// Every foreign cell touching one of the critter's cells within the power bought reach of the Whisker
+pub fn touch(world_data: &WorldData, soul_id: &String, x: i32, y: i32, power: i16, b_ps: &BPs) -> Vec<Contact> {
+    let reach = (power / b_ps.C_AEtoReach.max(1)) as i32;
+    let Some(critter) = body::soul_body(world_data, soul_id) else {
+        return Vec::new();
+    };
+
+    let mut felt = HashSet::new();
+    let mut contacts = Vec::new();
+    for &(bx, by) in &critter {
+        let (ox, oy) = world_data.offset_between((x, y), (bx as i32, by as i32));
+        if ox.abs().max(oy.abs()) > reach {
+            continue;
+        }
+
+        for dy in -1i32..=1 {
+            for dx in -1i32..=1 {
+                let Some(square) = world_data.resolve(bx as i32 + dx, by as i32 + dy) else {
+                    continue;
+                };
+                if critter.contains(&square) || world_data.critter_layer.is_empty(square) || !felt.insert(square) {
+                    continue;
+                }
+
+                let cell = world_data.critter_layer.get(square);
+                let (local_x, local_y) = world_data.global_to_local(soul_id, square.0 as i32, square.1 as i32);
+                contacts.push(Contact {
+                    x: local_x,
+                    y: local_y,
+                    kind: cell.kind,
+                    owner: (cell.owner != SoulHandle::NONE)
+                        .then(|| visual_pkg_generator::owner_tag(world_data, soul_id, world_data.souls.name(cell.owner))),
+                });
+            }
+        }
+    }
+
+    contacts
+}
+
+// Every recent Muscle push of another critter within the power bought hearing radius of the Ear
+pub fn hear(world_data: &WorldData, soul_id: &str, x: i32, y: i32, power: i16, b_ps: &BPs) -> Vec<Sound> {
+    let radius = (power / b_ps.C_AEtoHearing.max(1)) as i32;
+    let listener = world_data.souls.handle(soul_id);
+
+    world_data.vibrations.iter()
+        .filter(|vibration| Some(vibration.owner) != listener)
+        .filter_map(|vibration| {
+            let (dx, dy) = world_data.offset_between((x, y), (vibration.x, vibration.y));
+            let distance = ((dx * dx + dy * dy) as f32).sqrt().round() as i32;
+            (distance <= radius).then(|| Sound {
+                bearing: bearing_of(dx as f32, -dy as f32).unwrap_or(0),
+                distance,
+                strength: (vibration.strength as i32 / (1 + distance)) as i16,
+                ticks_ago: world_data.tick - vibration.tick,
+            })
+        })
+        .collect()
+}
+
+// Degrees clockwise from north of a local direction, +y being north. None if there is no direction.
+fn bearing_of(east: f32, north: f32) -> Option<u16> {
+    if east.abs() < f32::EPSILON && north.abs() < f32::EPSILON {
+        return None;
+    }
+    Some((east.atan2(north).to_degrees().round() as i32).rem_euclid(360) as u16)
+}
+
+// Called by Muscles whenever they push, so nearby Ears can hear it
+pub fn record_vibration(world_data: &mut WorldData, soul_id: &str, x: i32, y: i32, strength: i16) {
+    let Some(owner) = world_data.souls.handle(soul_id) else {
+        return;
+    };
+    let tick = world_data.tick;
+    world_data.vibrations.push(Vibration { x, y, owner, strength, tick });
+}
+
+// Forgets pushes that happened too long ago to be heard
+pub fn fade_vibrations(world_data: &mut WorldData, b_ps: &BPs) {
+    let tick = world_data.tick;
+    world_data.vibrations.retain(|vibration| tick - vibration.tick < b_ps.VibrationTicks.max(0) as u64);
+}
+
+// Sends a sense package to the client controlling the soul as JSON in a Sense envelope. Clients that have not turned
+// envelopes on get no sense packages, they would take them for visual packages.
+pub async fn send_sense_pkg(world_data: &WorldData, server_data: &Arc<Mutex<ServerData>>, soul_id: &str, pkg: &SensePkg) {
+    let bytes = serde_json::to_vec(&Envelope::Sense(pkg)).expect("Failed to serialize sense package");
+    let controller = crate::lifecycle::root_of(world_data, soul_id);
+    let tx = {
+        let server_data = server_data.lock().await;
+        server_data.get_envelope(controller).then(|| server_data.get_tx_channel(controller)).flatten()
+    };
+    if let Some(tx) = tx && let Err(e) = tx.send(Message::Binary(bytes)) {
+        eprintln!("Failed to send sense package: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell_def::{Cell, Orientation};
+
+    #[test]
+    fn smell_reaches_as_far_as_the_power_buys() {
+        let b_ps = BPs::new();
+        let mut world_data = WorldData::new(20, 20);
+        world_data.world[(13, 10)] = 200; // 3 squares east of the Nose
+
+        let short = smell(&world_data, 10, 10, 2 * b_ps.C_AEtoSmell, &b_ps);
+        assert_eq!(short.radius, 2);
+        assert_eq!((short.concentration, short.bearing), (0, None));
+
+        let long = smell(&world_data, 10, 10, 3 * b_ps.C_AEtoSmell, &b_ps);
+        assert_eq!(long.radius, 3);
+        assert!(long.concentration > 0);
+        assert_eq!(long.bearing, Some(90));
+
+        world_data.world[(13, 10)] = 0;
+        world_data.world[(10, 7)] = 200; // 3 squares north, global y grows southwards
+        assert_eq!(smell(&world_data, 10, 10, 3 * b_ps.C_AEtoSmell, &b_ps).bearing, Some(0));
+        assert_eq!(smell(&world_data, 10, 10, 0, &b_ps).radius, 1);
+    }
+
+    // Alice is a row of three cells from (5, 5) east to (7, 5), Bob's cell touches the far end
+    #[test]
+    fn whiskers_feel_contacts_within_their_reach() {
+        let b_ps = BPs::new();
+        let mut world_data = WorldData::new(20, 20);
+        let alice = world_data.souls.intern("alice");
+        let bob = world_data.souls.intern("bob");
+        world_data.critter_layer.set((5, 5), Cell::new(alice, CellKind::Whisker, 100, Orientation::C));
+        world_data.critter_layer.set((6, 5), Cell::new(alice, CellKind::Soul, 100, Orientation::C));
+        world_data.critter_layer.set((7, 5), Cell::new(alice, CellKind::Tissue, 100, Orientation::C));
+        world_data.critter_layer.set((8, 5), Cell::new(bob, CellKind::Armor, 100, Orientation::C));
+        world_data.soul_locations.push(("alice".to_string(), 6, 5));
+        let alice_id = "alice".to_string();
+
+        assert!(touch(&world_data, &alice_id, 5, 5, b_ps.C_AEtoReach, &b_ps).is_empty());
+
+        let contacts = touch(&world_data, &alice_id, 5, 5, 2 * b_ps.C_AEtoReach, &b_ps);
+        assert_eq!(contacts.len(), 1);
+        assert_eq!((contacts[0].x, contacts[0].y, contacts[0].kind), (2, 0, CellKind::Armor));
+        assert!(contacts[0].owner.is_some());
+    }
+
+    #[test]
+    fn sounds_fade_with_distance_and_stop_at_the_hearing_radius() {
+        let b_ps = BPs::new();
+        let mut world_data = WorldData::new(20, 20);
+        world_data.souls.intern("alice");
+        world_data.souls.intern("bob");
+        world_data.tick = 5;
+        record_vibration(&mut world_data, "bob", 13, 10, 400); // 3 squares east of the Ear
+        record_vibration(&mut world_data, "alice", 11, 10, 400); // Alice's own Muscle
+        world_data.tick = 6;
+
+        assert!(hear(&world_data, "alice", 10, 10, 2 * b_ps.C_AEtoHearing, &b_ps).is_empty());
+
+        let sounds = hear(&world_data, "alice", 10, 10, 3 * b_ps.C_AEtoHearing, &b_ps);
+        assert_eq!(sounds.len(), 1);
+        assert_eq!((sounds[0].bearing, sounds[0].distance, sounds[0].ticks_ago), (90, 3, 1));
+        assert_eq!(sounds[0].strength, 400 / 4);
+
+        let closer = hear(&world_data, "alice", 12, 10, 3 * b_ps.C_AEtoHearing, &b_ps);
+        assert_eq!((closer[0].distance, closer[0].strength), (1, 400 / 2));
+    }
+}
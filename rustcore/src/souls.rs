@@ -0,0 +1,85 @@
+// This file houses the soul table. Cells only carry a SoulHandle, the table maps handles back to soul ids and the other way.
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+use crate::cell_def::SoulHandle;
+
+#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct SoulTable {
+    names: Vec<String>, // Soul id of every handle, handle 0 belongs to nobody
+    handles: HashMap<String, SoulHandle>,
+    free: Vec<SoulHandle>, // Handles of dead souls, handed out again before the table grows
+}
+
+impl SoulTable {
+    pub fn new() -> Self {
+        Self {
+            names: vec!["Null".to_string()],
+            handles: HashMap::new(),
+            free: Vec::new(),
+        }
+    }
+
+    // Handle of a soul id, handing out one the first time the id is seen. A soul gets a fresh handle each time it spawns.
+    pub fn intern(&mut self, soul_id: &str) -> SoulHandle {
+        if let Some(handle) = self.handles.get(soul_id) {
+            return *handle;
+        }
+
+        let handle = match self.free.pop() {
+            Some(handle) => {
+                self.names[handle.0 as usize] = soul_id.to_string();
+                handle
+            }
+            None => {
+                self.names.push(soul_id.to_string());
+                SoulHandle(self.names.len() as u32 - 1)
+            }
+        };
+        self.handles.insert(soul_id.to_string(), handle);
+        handle
+    }
+
+    // Frees the handle of a dead soul for reuse. Nothing may still refer to it, kill_soul turns its cells into corpses first.
+    pub fn release(&mut self, soul_id: &str) {
+        if let Some(handle) = self.handles.remove(soul_id) {
+            self.names[handle.0 as usize].clear();
+            self.free.push(handle);
+        }
+    }
+
+    // Handles in use, not counting the one that belongs to nobody
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    // None for ids that never owned a cell, so a lookup can never match the cells that belong to nobody
+    pub fn handle(&self, soul_id: &str) -> Option<SoulHandle> {
+        self.handles.get(soul_id).copied()
+    }
+
+    pub fn name(&self, handle: SoulHandle) -> &str {
+        &self.names[handle.0 as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn released_handles_are_reused() {
+        let mut souls = SoulTable::new();
+        let parent = souls.intern("parent");
+        let child = souls.intern("child");
+        assert_eq!(souls.intern("parent"), parent);
+
+        souls.release("child");
+        assert_eq!(souls.handle("child"), None);
+        let sibling = souls.intern("sibling");
+        assert_eq!(sibling, child);
+        assert_eq!(souls.name(sibling), "sibling");
+        assert_eq!(souls.len(), 2);
+    }
+}
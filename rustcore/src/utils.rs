@@ -2,8 +2,18 @@ use rand::Rng;
 
 use crate::cell_def;
 use crate::visual_pkg_generator;
-
-use cell_def::{Cell, CellKind};
+use crate::movement;
+use crate::combat;
+use crate::lifecycle;
+use crate::body;
+use crate::worldgen;
+use crate::senses;
+use crate::chunks::ChunkedGrid;
+use crate::critter_layer::CritterLayer;
+
+use cell_def::{Cell, CellKind, Orientation};
+use worldgen::{Biome, Terrain};
+use senses::SensePkg;
 use std::io::{self, Write};
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -15,55 +25,46 @@ use crate::ServerData;
 
 use tungstenite::protocol::Message;
 
-pub fn generate_world(size: usize) -> Vec<Vec<u8>> {
-    let mut rng = rand::thread_rng();
-
-    // Step 1: Initialize world with random values 0..=255
-    let mut world: Vec<Vec<u8>> = (0..size)
-        .map(|_| (0..size).map(|_| rng.gen_range(0..=255)).collect())
-        .collect();
-
-    // Step 2: Smooth the world multiple times to reduce sharp jumps
-    let smoothing_passes = 2;
-    for _ in 0..smoothing_passes {
-        world = smooth(&world, size);
+// Generates the food layer, the biome map and the terrain layer from three noise fields. Elevation picks water and walls,
+// moisture picks the other biomes, and a finer detail field scatters rock and soil.
+pub fn generate_world(width: usize, height: usize, wrap: bool, rng: &mut impl Rng, b_ps: &BPs) -> (ChunkedGrid<u8>, ChunkedGrid<Biome>, ChunkedGrid<Terrain>) {
+    let elevation = worldgen::Noise::new(rng);
+    let moisture = worldgen::Noise::new(rng);
+    let detail = worldgen::Noise::new(rng);
+
+    let mut world = ChunkedGrid::new(width, height, 0u8);
+    let mut biomes = ChunkedGrid::new(width, height, Biome::Grassland);
+    let mut terrain = ChunkedGrid::new(width, height, Terrain::Open);
+
+    for y in 0..height {
+        for x in 0..width {
+            let e = elevation.at_square(x, y, width, height, wrap, b_ps.TerrainScale, b_ps);
+            let m = moisture.at_square(x, y, width, height, wrap, b_ps.TerrainScale, b_ps);
+            let d = detail.at_square(x, y, width, height, wrap, b_ps.TerrainDetailScale, b_ps);
+            let biome = worldgen::classify(e, m, b_ps);
+            let ground = worldgen::place_terrain(biome, d, b_ps);
+
+            // Wetter squares hold more food than dry squares of the same biome, and nothing grows on rock or water
+            let fertility = if ground.is_passable() { b_ps.BiomeFertility.get(&biome).copied().unwrap_or(0) as f32 } else { 0.0 };
+            world.set(x, y, (fertility * (0.75 + 0.25 * m)).round().clamp(0.0, 255.0) as u8);
+            biomes.set(x, y, biome);
+            terrain.set(x, y, ground);
+        }
     }
 
-    world
+    (world, biomes, terrain)
 }
 
-// Helper function: smooth by averaging neighbors (3x3 kernel)
-fn smooth(world: &Vec<Vec<u8>>, size: usize) -> Vec<Vec<u8>> {
-    let mut new_world = vec![vec![0u8; size]; size];
-
-    for i in 0..size {
-        for j in 0..size {
-            let mut sum = 0usize;
-            let mut count = 0usize;
-
-            for dy in -1i32..=1 {
-                for dx in -1i32..=1 {
-                    let y = i as i32 + dy;
-                    let x = j as i32 + dx;
-
-                    if x >= 0 && x < size as i32 && y >= 0 && y < size as i32 {
-                        sum += world[y as usize][x as usize] as usize;
-                        count += 1;
-                    }
-                }
-            }
-
-            new_world[i][j] = (sum / count) as u8;
-        }
-    }
-
-    new_world
+// How many squares each printed character stands for, so a map is never printed more than max_size characters across
+pub fn console_step(width: usize, height: usize, max_size: i16) -> usize {
+    width.max(height).div_ceil(max_size.max(1) as usize).max(1)
 }
 
-pub fn visualize_world_console(world: &Vec<Vec<u8>>) {
-    for row in world {
-        for &val in row {
-            let c = match val {
+pub fn visualize_world_console(world: &ChunkedGrid<u8>, max_size: i16) {
+    let step = console_step(world.width(), world.height(), max_size);
+    for y in (0..world.height()).step_by(step) {
+        for x in (0..world.width()).step_by(step) {
+            let c = match world[(x, y)] {
                 0..=51 => ' ',
                 52..=102 => '░',
                 103..=153 => '▒',
@@ -76,18 +77,7 @@ pub fn visualize_world_console(world: &Vec<Vec<u8>>) {
     }
 }
 
-pub fn the_sun(world: &mut Vec<Vec<u8>>) {
-
-    for i in 0..world.len() {
-        for j in 0..world[i].len() {
-            if world[i][j] < 255 {
-                world[i][j] += 1;
-            }
-        }
-    }
-}
-
-pub fn build_critters(critter_layer: &mut Vec<Vec<Cell>>, build_que: &mut Vec<UserInput>, b_ps: &BPs) {
+pub async fn build_critters(world_data: &mut WorldData, build_que: &mut [UserInput], b_ps: &BPs, server_data: &Arc<Mutex<ServerData>>) {
     for input in build_que.iter() {
         if let UserInput::Build {
             soul_id,
@@ -104,71 +94,207 @@ pub fn build_critters(critter_layer: &mut Vec<Vec<Cell>>, build_que: &mut Vec<Us
                 continue;
             };
 
-            let direction = dir.clone();
-            if !Cell::valid_dir(direction.as_str()) {
+            let Some(base_cost) = b_ps.BuildCost.get(&cell_kind) else {
+                println!("Block type {} cannot be built", block_type);
+                continue;
+            };
+
+            let Some(direction) = Orientation::from_input_string(dir) else {
                 println!("Invalid direction: {}", dir);
                 continue;
+            };
+
+            if *power < 0 {
+                println!("Build power cannot be negative: {}", power);
+                continue;
             }
 
             // Bounds check
-            if *Y as usize >= critter_layer.len() || *X as usize >= critter_layer[0].len() {
+            if !world_data.is_in_bounds(*X, *Y) {
                 println!("Build request out of bounds: ({}, {})", X, Y);
                 continue;
             }
 
-            let existing_cell = &critter_layer[*Y as usize][*X as usize];
-            if existing_cell.kind == cell_kind && existing_cell.id == *soul_id{
-                //If the build is on an existing cell, modify energy
-                critter_layer[*Y as usize][*X as usize].energy += *power;
+            if !world_data.is_passable(*X, *Y) {
+                send_text(world_data, server_data, soul_id, format!("Cannot build at ({}, {}), the ground is impassable", X, Y)).await;
+                continue;
             }
-            else if existing_cell.kind != CellKind::Empty {
+
+            let existing_kind = world_data.critter_layer.kind((*X as usize, *Y as usize));
+            if existing_kind == cell_kind && world_data.is_owned_by(soul_id, *X as usize, *Y as usize) {
+                //If the build is on an existing cell, modify energy, this only costs the energy moved into the cell
+                if !draw_energy(world_data, soul_id, *power) {
+                    send_text(world_data, server_data, soul_id, format!("Cannot afford to add {} Energy to the cell at ({}, {})", power, X, Y)).await;
+                    continue;
+                }
+                let energy = world_data.critter_layer.energy_mut((*X as usize, *Y as usize));
+                *energy = energy.saturating_add(*power);
+            }
+            else if existing_kind != CellKind::Empty {
                 //If the existing cell is not empty, error out
                 println!("Cell at ({}, {}) is not empty, cannot build", X, Y);
                 continue;
             } else {
-                //Check if cell is valid for building a new cell and build it
-                let mut can_build = false;
-                let size = critter_layer.len();
-                let x = *X as isize;
-                let y = *Y as isize;
+                //Check if cell is valid for building a new cell and build it. New cells can only attach to a Soul
+                //or Tissue cell that is part of the builder's own connected body
+                let Some(builder_body) = body::soul_body(world_data, soul_id) else {
+                    println!("Soul {} has no body to build on", soul_id);
+                    continue;
+                };
+
                 let directions = [(0, 1), (1, 0), (0, -1), (-1, 0), (1, 1), (1, -1), (-1, 1), (-1, -1)];
 
-                for (dx, dy) in directions.iter() {
-                    let nx = x + dx;
-                    let ny = y + dy;
-                    if nx >= 0 && nx < size as isize && ny >= 0 && ny < size as isize {
-                        let neighbor = &critter_layer[ny as usize][nx as usize];
-                        if neighbor.kind == CellKind::Tissue
-                            || (neighbor.kind == CellKind::Soul && neighbor.id == *soul_id)
-                        {
-                            can_build = true;
-                            break;
-                        }
-                    }
-                }
+                let can_build = directions.iter().any(|(dx, dy)| {
+                    world_data.resolve(*X + dx, *Y + dy).is_some_and(|(nx, ny)| {
+                        builder_body.contains(&(nx, ny))
+                            && matches!(world_data.critter_layer.kind((nx, ny)), CellKind::Tissue | CellKind::Soul)
+                    })
+                });
 
                 if !can_build {
                     println!(
-                        "Cannot build at ({}, {}): no adjacent Tissue or matching Soul cell",
-                        X, Y
+                        "Cannot build at ({}, {}): no adjacent Tissue or Soul cell of {}'s body",
+                        X, Y, soul_id
                     );
                     continue;
                 }
 
-                let cell_kind = CellKind::from_input_string(block_type).expect("Invalid block type");
+                // A new cell costs its base construction cost plus the energy it starts with
+                let cost = base_cost.saturating_add(*power);
+                if !draw_energy(world_data, soul_id, cost) {
+                    send_text(world_data, server_data, soul_id, format!("Cannot afford to build {} at ({}, {}), it costs {} Energy", block_type, X, Y, cost)).await;
+                    continue;
+                }
 
                 // Place the cell
-                critter_layer[*Y as usize][*X as usize] =
-                    Cell::new(soul_id.clone(), cell_kind, *power, direction.clone());
+                let owner = world_data.souls.intern(soul_id);
+                world_data.critter_layer.set((*X as usize, *Y as usize), Cell::new(owner, cell_kind, *power, direction));
             }
         }
     }
 }
 
-pub fn visualize_critter_layer(critter_layer: &Vec<Vec<Cell>>) {
-    for row in critter_layer {
-        for cell in row {
-            let c = match cell.kind {
+pub async fn demolish_critters(world_data: &mut WorldData, demolish_que: &[UserInput], b_ps: &BPs, server_data: &Arc<Mutex<ServerData>>) {
+    for input in demolish_que.iter() {
+        let UserInput::Demolish { soul_id, X, Y } = input else {
+            continue;
+        };
+
+        if !world_data.is_in_bounds(*X, *Y) {
+            println!("Demolish request out of bounds: ({}, {})", X, Y);
+            continue;
+        }
+
+        let cell = world_data.critter_layer.get((*X as usize, *Y as usize));
+        if !world_data.is_owned_by(soul_id, *X as usize, *Y as usize) {
+            println!("Cell at ({}, {}) is not owned by {}, cannot demolish", X, Y, soul_id);
+            continue;
+        } else if cell.kind == CellKind::Soul {
+            send_text(world_data, server_data, soul_id, "A Soul cannot demolish itself".to_string()).await;
+            continue;
+        }
+
+        let Some(&(_, soul_x, soul_y)) = world_data.soul_locations.iter().find(|(s, _, _)| s == soul_id) else {
+            continue;
+        };
+
+        let refund = (cell.energy.max(0) as i32 * b_ps.DemolishRefund as i32 / 100) as i16;
+        world_data.critter_layer.clear((*X as usize, *Y as usize));
+        let soul_energy = world_data.critter_layer.energy_mut((soul_x as usize, soul_y as usize));
+        *soul_energy = soul_energy.saturating_add(refund);
+
+        // Anything that was only attached through the demolished cell falls off
+        let detached = body::orphan_detached(world_data, soul_id);
+
+        let mut report = format!("Demolished the cell at ({}, {}), refunded {} Energy", X, Y, refund);
+        if detached > 0 {
+            report.push_str(&format!(", {} detached cells became corpses", detached));
+        }
+        send_text(world_data, server_data, soul_id, report).await;
+    }
+}
+
+pub async fn rotate_cells(world_data: &mut WorldData, rotate_que: &[UserInput], b_ps: &BPs, server_data: &Arc<Mutex<ServerData>>) {
+    for input in rotate_que.iter() {
+        let UserInput::Rotate { soul_id, X, Y, dir } = input else {
+            continue;
+        };
+
+        let Some(direction) = Orientation::from_input_string(dir) else {
+            println!("Invalid direction: {}", dir);
+            continue;
+        };
+
+        if !world_data.is_in_bounds(*X, *Y) {
+            println!("Rotate request out of bounds: ({}, {})", X, Y);
+            continue;
+        }
+
+        let cell = world_data.critter_layer.get((*X as usize, *Y as usize));
+        if !world_data.is_owned_by(soul_id, *X as usize, *Y as usize) {
+            println!("Cell at ({}, {}) is not owned by {}, cannot rotate", X, Y, soul_id);
+            continue;
+        } else if !cell.can_rotate() {
+            send_text(world_data, server_data, soul_id, format!("A {:?} cannot be rotated", cell.kind)).await;
+            continue;
+        } else if cell.orientation == direction {
+            continue;
+        }
+
+        if !draw_energy(world_data, soul_id, b_ps.RotateCost) {
+            send_text(world_data, server_data, soul_id, format!("Cannot afford to rotate the cell at ({}, {}), it costs {} Energy", X, Y, b_ps.RotateCost)).await;
+            continue;
+        }
+
+        world_data.critter_layer.set_orientation((*X as usize, *Y as usize), direction);
+    }
+}
+
+// Debits `amount` energy from a soul, taking from the Soul cell first and then from Tissue connected to it.
+// Nothing is debited and false is returned if the soul cannot afford the whole amount.
+pub fn draw_energy(world_data: &mut WorldData, soul_id: &str, amount: i16) -> bool {
+    let Some(&(_, soul_x, soul_y)) = world_data.soul_locations.iter().find(|(s, _, _)| s == soul_id) else {
+        return false;
+    };
+    let (soul_x, soul_y) = (soul_x as usize, soul_y as usize);
+
+    let tissue: Vec<(usize, usize)> = body::connected_cells(world_data, soul_id, soul_x, soul_y)
+        .into_iter()
+        .filter(|(x, y)| world_data.critter_layer.kind((*x, *y)) == CellKind::Tissue)
+        .collect();
+
+    let available: i32 = std::iter::once((soul_x, soul_y))
+        .chain(tissue.iter().copied())
+        .map(|(x, y)| world_data.critter_layer.get((x, y)).energy.max(0) as i32)
+        .sum();
+    if available < amount as i32 {
+        return false;
+    }
+
+    let mut remaining = amount;
+    for (x, y) in std::iter::once((soul_x, soul_y)).chain(tissue) {
+        let energy = world_data.critter_layer.energy_mut((x, y));
+        let taken = remaining.min((*energy).max(0));
+        *energy -= taken;
+        remaining -= taken;
+        if remaining == 0 {
+            break;
+        }
+    }
+
+    true
+}
+
+// Only allocated chunks can hold critters, so only those are looked at. On a sampled down map a character shows any
+// critter cell in the block it stands for.
+pub fn visualize_critter_layer(critter_layer: &CritterLayer, max_size: i16) {
+    let step = console_step(critter_layer.width(), critter_layer.height(), max_size);
+    let mut canvas = vec![vec!['.'; critter_layer.width().div_ceil(step)]; critter_layer.height().div_ceil(step)];
+
+    for (x, y) in critter_layer.occupied_squares() {
+        let kind = critter_layer.kind((x, y));
+        if kind != CellKind::Empty {
+            canvas[y / step][x / step] = match kind {
                 CellKind::Tissue => 'T',
                 CellKind::Soul => 'S',
                 CellKind::Eyeball => 'O',
@@ -177,12 +303,18 @@ pub fn visualize_critter_layer(critter_layer: &Vec<Vec<Cell>>) {
                 CellKind::Muscle => 'U',
                 CellKind::Anchor => 'A',
                 CellKind::Armor => '#',
+                CellKind::Corpse => 'x',
+                CellKind::Nose => 'N',
+                CellKind::Whisker => 'W',
+                CellKind::Ear => 'E',
                 CellKind::Empty => '.', // For empty cells
                 _ => '.', // For empty or unknown cells
             };
-            print!("{}", c);
         }
-        println!();
+    }
+
+    for row in canvas {
+        println!("{}", row.into_iter().collect::<String>());
     }
 }
 
@@ -202,14 +334,25 @@ pub fn generate_souls(world_data: &mut WorldData, soul_que: & Vec<UserInput>, st
             continue; // Skip if soul already exists
         }
 
-        let mut x_spawn = (rand::thread_rng().gen_range(0..world_data.world.len())) as usize;
-        let mut y_spawn = (rand::thread_rng().gen_range(0..world_data.world[0].len())) as usize;
+        if lifecycle::parent_of(world_data, &soul_id_to_find).is_some() {
+            println!("Offspring soul {} can only be born through Reproduce", soul_id_to_find);
+            continue;
+        }
+
+        let cooldown = lifecycle::respawn_cooldown(world_data, &soul_id_to_find);
+        if cooldown > 0 {
+            println!("Soul {} died recently and can respawn in {} ticks", soul_id_to_find, cooldown);
+            continue; // Skip while the soul is still on cooldown
+        }
+
+        let mut x_spawn = world_data.rng.random_range(0..world_data.width());
+        let mut y_spawn = world_data.rng.random_range(0..world_data.height());
 
         let mut i = 0; // Counter to prevent infinite loop
-        while !is_empty_cell(&world_data, x_spawn.try_into().unwrap(), y_spawn.try_into().unwrap(), 3) && i < 100 {
+        while (!is_empty_cell(&world_data, x_spawn.try_into().unwrap(), y_spawn.try_into().unwrap(), 3) || !world_data.is_passable(x_spawn as i32, y_spawn as i32)) && i < 100 {
             // If the cell is not empty, find a new random position
-            x_spawn = rand::thread_rng().gen_range(0..world_data.world.len());
-            y_spawn = rand::thread_rng().gen_range(0..world_data.world[0].len());
+            x_spawn = world_data.rng.random_range(0..world_data.width());
+            y_spawn = world_data.rng.random_range(0..world_data.height());
             i += 1; // Increment counter
         }
 
@@ -219,9 +362,11 @@ pub fn generate_souls(world_data: &mut WorldData, soul_que: & Vec<UserInput>, st
         }
 
         // Create a new soul cell
-        let new_soul_cell = Cell::new(soul_id_to_find.clone(), CellKind::Soul, starting_energy, "C".to_string());
-        world_data.critter_layer[y_spawn][x_spawn] = new_soul_cell; // Place the soul in the critter layer
+        let owner = world_data.souls.intern(&soul_id_to_find);
+        let new_soul_cell = Cell::new(owner, CellKind::Soul, starting_energy, Orientation::C);
+        world_data.critter_layer.set((x_spawn, y_spawn), new_soul_cell); // Place the soul in the critter layer
         world_data.soul_locations.push((soul_id_to_find.clone(), x_spawn.try_into().unwrap(), y_spawn.try_into().unwrap())); // Add to soul locations
+        world_data.respawn_at.remove(&soul_id_to_find);
         println!("Generated soul {} at ({}, {})", soul_id_to_find, x_spawn, y_spawn);
     }
     
@@ -232,28 +377,16 @@ pub fn is_empty_cell(world_data: &WorldData, x: usize, y: usize, r:usize) -> boo
 
     println!("Checking if cell at ({}, {}) is empty within radius {}", x, y, r);
 
-    if x > world_data.world.len() || y > world_data.world[0].len() {
+    if !world_data.is_in_bounds(x as i32, y as i32) {
         return false;
-    } else if x <= 0 || y <= 0 {
-        return false; 
-    }
-
-    let rows = world_data.world.len();
-    if rows == 0 {
-        return false; // Empty world
     }
 
-    let cols = world_data.world[0].len();
-
-    // Calculate the boundaries for the radius search, clamp to world size
-    let start_x = x.saturating_sub(r); // avoid underflow
-    let end_x = (x + r).min(rows - 1);
-    let start_y = y.saturating_sub(r);
-    let end_y = (y + r).min(cols - 1);
-
-    for i in start_x..=end_x {
-        for j in start_y..=end_y {
-            if !world_data.critter_layer[i][j].is_empty() {
+    // Squares past the edge of a bounded world don't count, on a wrapping world the search wraps with it
+    let r = r as i32;
+    for dy in -r..=r {
+        for dx in -r..=r {
+            if let Some((nx, ny)) = world_data.resolve(x as i32 + dx, y as i32 + dy)
+                && !world_data.critter_layer.is_empty((nx, ny)) {
                 return false; // Found a non-empty cell
             }
         }
@@ -264,57 +397,63 @@ pub fn is_empty_cell(world_data: &WorldData, x: usize, y: usize, r:usize) -> boo
 
 pub async fn do_actions(world_data: &mut WorldData, action_que: & Vec<UserInput>, b_ps: &BPs, server_data: &Arc<tokio::sync::Mutex<ServerData>>){
     for action in action_que{
-        let UserInput::Activate { soul_id, delay, X, Y, power } = action else {
+        // Converted here rather than when queued, since a Muscle earlier in the que may have moved the critter
+        let Some(action) = action.clone().local_to_global(world_data) else {
+            continue;
+        };
+        let UserInput::Activate { soul_id, X, Y, power, .. } = &action else {
             println!("Invalid action: {:?}", action);
             continue;
         };
 
         //Checks befor activating cell
-        if world_data.critter_layer[*Y as usize][*X as usize].is_empty() {
+        if !world_data.is_in_bounds(*X, *Y) {
+            println!("Activation out of bounds: ({}, {})", X, Y);
+            continue;
+        } else if world_data.critter_layer.is_empty((*X as usize, *Y as usize)) {
             println!("Cell at ({}, {}) is empty", X, Y); //eventually this should be returned to user!!
             continue;
-        } else if world_data.critter_layer[*Y as usize][*X as usize].id != *soul_id {
+        } else if !world_data.is_owned_by(soul_id, *X as usize, *Y as usize) {
             println!("Cell at ({}, {}) is not owned by you!", X, Y);
             continue;
         }
 
-        match world_data.critter_layer[*Y as usize][*X as usize].kind {
+        let cell = world_data.critter_layer.get((*X as usize, *Y as usize));
+        match cell.kind {
             CellKind::Soul => {
                 println!("Cell at ({}, {}) is a soul, not a valid target", X, Y);
             },
             CellKind::Tissue => {
                 println!("That cell is a tissue, not a valid target");
-                let tx = server_data.lock().await.get_tx_channel(soul_id);
+                let tx = server_data.lock().await.get_tx_channel(lifecycle::root_of(world_data, soul_id));
                 if let Some(tx) = tx {
                     tx.send(Message::Text(format!("That cell is a tissue, not a valid target")));
                 }
             },
             CellKind::Eyeball => {
                 println!("Cell at ({}, {}) is an eyeball", X, Y);
-                let EE = world_data.critter_layer[*Y as usize][*X as usize].energy;
+                let EE = cell.energy;
                 let O_u = EE * b_ps.C_EEtoAE; //Upper bound of allowable activation energy
                 let O_l = (O_u as f32 - O_u as f32 * (b_ps.C_E_percent as f32 / 100.0)).round() as i16; // Lower bound of allowable activation energy
                 
                 //Checking if the activiation energy is withen allowable and returning an error if not
                 if *power > O_u || *power < O_l {
-                    let tx = server_data.lock().await.get_tx_channel(soul_id);
+                    let tx = server_data.lock().await.get_tx_channel(lifecycle::root_of(world_data, soul_id));
                     if let Some(tx) = tx {
                         tx.send(Message::Text(format!("This Eyeball requires {} to {} Energy", O_u, O_l)));
                     }
                     continue;
                 }
 
-                let dir = &world_data.critter_layer[*Y as usize][*X as usize].orientation;
-                let mut radius = 0;
-                if dir == "C" {
-                    radius = (*power as f32 / b_ps.C_AEtoAction_cent as f32).round() as i32;
-                } else {
-                    radius = (*power as f32 / b_ps.C_AEtoAction_dir as f32).round() as i32;
-                }
-
-                let visual_pkg = visual_pkg_generator::generate_visual_pkg(&world_data, soul_id, X, Y, radius, dir, &b_ps.DirectionalEyeballFOV);
+                let visible = visual_pkg_generator::generate_visual_pkg(&world_data, soul_id, X, Y, *power, cell.orientation, b_ps);
+                let (format, enveloped) = {
+                    let server_data = server_data.lock().await;
+                    let controller = lifecycle::root_of(world_data, soul_id);
+                    (server_data.get_visual_format(controller), server_data.get_envelope(controller))
+                };
+                let visual_pkg = visual_pkg_generator::encode_visual_pkg(&visible, format, enveloped);
                 // Send the visual package to the client
-                let tx = server_data.lock().await.get_tx_channel(soul_id);
+                let tx = server_data.lock().await.get_tx_channel(lifecycle::root_of(world_data, soul_id));
                 if let Some(tx) = tx {
                     if let Err(e) = tx.send(Message::Binary(visual_pkg)) {
                         eprintln!("Failed to send visual package: {}", e);
@@ -323,18 +462,103 @@ pub async fn do_actions(world_data: &mut WorldData, action_que: & Vec<UserInput>
             },
             CellKind::Mouth => {
                 println!("Cell at ({}, {}) is a mouth", X, Y);
+                let ME = cell.energy;
+                let max_power = ME.saturating_mul(b_ps.C_MEtoAE); //Upper bound of allowable activation energy
+
+                if *power <= 0 || *power > max_power {
+                    send_text(world_data, server_data, soul_id, format!("This Mouth accepts 1 to {} Energy", max_power)).await;
+                    continue;
+                }
+
+                let bite = *power / b_ps.C_AEtoBite;
+                mouth_eat(world_data, soul_id, *X, *Y, bite, b_ps, server_data).await;
             },
             CellKind::Butt => {
                 println!("Cell at ({}, {}) is a butt", X, Y);
+                let BuE = cell.energy;
+                let max_power = BuE.saturating_mul(b_ps.C_BuEtoAE); //Upper bound of allowable activation energy
+
+                if *power <= 0 || *power > max_power {
+                    send_text(world_data, server_data, soul_id, format!("This Butt accepts 1 to {} Energy", max_power)).await;
+                    continue;
+                }
+
+                let amount = *power / b_ps.C_AEtoExcrete;
+                let report = butt_excrete(world_data, soul_id, *X, *Y, amount);
+                send_text(world_data, server_data, soul_id, report).await;
             },
             CellKind::Muscle => {
                 println!("Cell at ({}, {}) is a muscle", X, Y);
+                let MuE = cell.energy;
+                let max_power = MuE.saturating_mul(b_ps.C_MuEtoAE); //Upper bound of allowable activation energy
+
+                if *power <= 0 || *power > max_power {
+                    send_text(world_data, server_data, soul_id, format!("This Muscle accepts 1 to {} Energy", max_power)).await;
+                    continue;
+                }
+
+                let report = movement::muscle_push(world_data, soul_id, *X, *Y, *power, b_ps);
+                send_text(world_data, server_data, soul_id, report).await;
             },
             CellKind::Armor => {
                 println!("Cell at ({}, {}) is an armor, not a valid target", X, Y);
             },
+            CellKind::Nose => {
+                println!("Cell at ({}, {}) is a nose", X, Y);
+                let max_power = cell.energy.saturating_mul(b_ps.C_NoEtoAE); //Upper bound of allowable activation energy
+
+                if *power <= 0 || *power > max_power {
+                    send_text(world_data, server_data, soul_id, format!("This Nose accepts 1 to {} Energy", max_power)).await;
+                    continue;
+                }
+
+                let smell = senses::smell(world_data, *X, *Y, *power, b_ps);
+                senses::send_sense_pkg(world_data, server_data, soul_id, &SensePkg::Smell(smell)).await;
+            },
+            CellKind::Whisker => {
+                println!("Cell at ({}, {}) is a whisker", X, Y);
+                let max_power = cell.energy.saturating_mul(b_ps.C_WhEtoAE); //Upper bound of allowable activation energy
+
+                if *power <= 0 || *power > max_power {
+                    send_text(world_data, server_data, soul_id, format!("This Whisker accepts 1 to {} Energy", max_power)).await;
+                    continue;
+                }
+
+                let contacts = senses::touch(world_data, soul_id, *X, *Y, *power, b_ps);
+                senses::send_sense_pkg(world_data, server_data, soul_id, &SensePkg::Touch(contacts)).await;
+            },
+            CellKind::Ear => {
+                println!("Cell at ({}, {}) is an ear", X, Y);
+                let max_power = cell.energy.saturating_mul(b_ps.C_EaEtoAE); //Upper bound of allowable activation energy
+
+                if *power <= 0 || *power > max_power {
+                    send_text(world_data, server_data, soul_id, format!("This Ear accepts 1 to {} Energy", max_power)).await;
+                    continue;
+                }
+
+                let sounds = senses::hear(world_data, soul_id, *X, *Y, *power, b_ps);
+                senses::send_sense_pkg(world_data, server_data, soul_id, &SensePkg::Vibration(sounds)).await;
+            },
             CellKind::Anchor => {
                 println!("Cell at ({}, {}) is an anchor", X, Y);
+                let AnE = cell.energy;
+                let max_power = AnE.saturating_mul(b_ps.C_AnEtoAE); //Upper bound of allowable activation energy
+
+                if *power <= 0 || *power > max_power {
+                    send_text(world_data, server_data, soul_id, format!("This Anchor accepts 1 to {} Energy", max_power)).await;
+                    continue;
+                }
+
+                let tick_cost = b_ps.C_AEtoAnchorTick.max(1);
+                if *power < tick_cost {
+                    send_text(world_data, server_data, soul_id, format!("This Anchor needs at least {} Energy to hold for a tick", tick_cost)).await;
+                    continue;
+                }
+
+                // Holds from now through the next `ticks` ticks. Re-activating refreshes the timer rather than stacking on top of it.
+                let ticks = (*power / tick_cost) as u64;
+                world_data.anchored.insert(soul_id.clone(), world_data.tick + ticks);
+                send_text(world_data, server_data, soul_id, format!("Anchors engaged for {} ticks", ticks)).await;
             },
             _ => {
                 println!("Cell at ({}, {}) is not a valid target", X, Y);
@@ -343,3 +567,94 @@ pub async fn do_actions(world_data: &mut WorldData, action_que: & Vec<UserInput>
     }
 }
 
+// Drains up to `bite` energy from whatever the mouth at (x, y) is facing, world food or an enemy cell,
+// and stores the digested share in the mouth. Reports the outcome to the client, and to the victim of a bite.
+pub async fn mouth_eat(world_data: &mut WorldData, soul_id: &String, x: i32, y: i32, bite: i16, b_ps: &BPs, server_data: &Arc<Mutex<ServerData>>) {
+    let (dx, dy) = world_data.critter_layer.get((x as usize, y as usize)).orientation.offset();
+    let Some((target_x, target_y)) = world_data.resolve(x + dx, y + dy).filter(|_| (dx, dy) != (0, 0)) else {
+        send_text(world_data, server_data, soul_id, "This Mouth is not facing anything edible".to_string()).await;
+        return;
+    };
+    let (target_x, target_y) = (target_x as i32, target_y as i32);
+
+    let gut = world_data.guts.get(soul_id).copied().unwrap_or(0);
+    if gut >= b_ps.GutCapacity {
+        send_text(world_data, server_data, soul_id, "This critter's gut is full, use a Butt before eating again".to_string()).await;
+        return;
+    }
+
+    // Only bite off as much as the gut has room for the undigested share of, the rest stays where it is
+    let undigested = 100 - b_ps.MouthEfficiency as i32;
+    let bite = if undigested > 0 {
+        bite.min(((b_ps.GutCapacity - gut) as i32 * 100 / undigested).min(i16::MAX as i32) as i16)
+    } else {
+        bite
+    };
+
+    let target = world_data.critter_layer.get((target_x as usize, target_y as usize));
+    let eaten = if target.is_empty() {
+        // Nothing living in front of the mouth, graze on the world layer
+        let food = &mut world_data.world[(target_x as usize, target_y as usize)];
+        let eaten = bite.min(*food as i16);
+        *food -= eaten as u8;
+        eaten
+    } else if world_data.is_owned_by(soul_id, target_x as usize, target_y as usize) {
+        send_text(world_data, server_data, soul_id, "A Mouth cannot eat its own critter".to_string()).await;
+        return;
+    } else if target.kind == CellKind::Corpse {
+        // Corpses don't fight back, they are eaten like food
+        let corpse = world_data.critter_layer.energy_mut((target_x as usize, target_y as usize));
+        let eaten = bite.min(*corpse).max(0);
+        *corpse -= eaten;
+        if *corpse <= 0 {
+            world_data.critter_layer.clear((target_x as usize, target_y as usize));
+        }
+        eaten
+    } else {
+        let outcome = combat::bite(world_data, x, y, target_x, target_y, bite, b_ps);
+        combat::report_bite(world_data, soul_id, &outcome, server_data).await;
+        if outcome.mouth_destroyed {
+            return;
+        }
+        outcome.eaten
+    };
+
+    // Food enters the critter at the mouth, tissue carries it to the rest of the body
+    let digested = (eaten as i32 * b_ps.MouthEfficiency as i32 / 100) as i16;
+    let mouth = world_data.critter_layer.energy_mut((x as usize, y as usize));
+    *mouth = mouth.saturating_add(digested);
+
+    // Whatever was not digested waits in the gut for a Butt
+    let waste = gut + eaten - digested;
+    world_data.guts.insert(soul_id.clone(), waste);
+
+    send_text(world_data, server_data, soul_id, format!("Mouth ate {} energy, {} was digested", eaten, digested)).await;
+}
+
+// Empties up to `amount` of the soul's gut onto the world square behind the butt at (x, y). A centered butt
+// excretes onto the square it sits on. Returns a report for the client.
+pub fn butt_excrete(world_data: &mut WorldData, soul_id: &String, x: i32, y: i32, amount: i16) -> String {
+    let (dx, dy) = world_data.critter_layer.get((x as usize, y as usize)).orientation.offset();
+    let Some((target_x, target_y)) = world_data.resolve(x - dx, y - dy) else {
+        return "This Butt is facing off the edge of the world".to_string();
+    };
+
+    let gut = world_data.guts.get(soul_id).copied().unwrap_or(0);
+    let food = &mut world_data.world[(target_x, target_y)];
+    // A square can only hold so much food, anything that does not fit stays in the gut
+    let excreted = amount.min(gut).min(255 - *food as i16).max(0);
+    *food += excreted as u8;
+    world_data.guts.insert(soul_id.clone(), gut - excreted);
+
+    format!("Butt excreted {} food, {} left in the gut", excreted, gut - excreted)
+}
+
+// Sends a text message to the client controlling a soul, if that soul has an active session.
+// Messages for offspring are tagged with their id since they arrive on the ancestor's session.
+pub async fn send_text(world_data: &WorldData, server_data: &Arc<Mutex<ServerData>>, soul_id: &str, text: String) {
+    let tx = server_data.lock().await.get_tx_channel(lifecycle::root_of(world_data, soul_id));
+    let text = if lifecycle::parent_of(world_data, soul_id).is_some() { format!("[{}] {}", soul_id, text) } else { text };
+    if let Some(tx) = tx && let Err(e) = tx.send(Message::Text(text)) {
+        eprintln!("Failed to send message: {}", e);
+    }
+}
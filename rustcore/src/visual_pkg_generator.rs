@@ -1,11 +1,18 @@
 // This file houses the function used to generate world packages given a center point, power level, and a few other key parameters
-use serde::Serialize;
+use serde::{Serialize, Deserialize};
 use std::f32::consts::PI;
+use std::collections::HashSet;
+use std::hash::Hasher;
+use siphasher::sip::SipHasher24;
 use bincode;
 
 use crate::cell_def;
 use crate::WorldData;
-use cell_def::{Cell, CellKind};
+use crate::BPs;
+use crate::lifecycle;
+use crate::senses::SensePkg;
+use crate::worldgen::Terrain;
+use cell_def::{CellKind, Orientation, SoulHandle};
 
 #[derive(Serialize, Clone)]
 pub struct Square {
@@ -14,65 +21,260 @@ pub struct Square {
     pub content: SquareKind,
 }
 
+// A critter cell as an eye sees it. The owner is "self" for the observer's own cells, None for corpses, and otherwise
+// a tag that stands in for the owner's soul id (see owner_tag). Energy of other souls' cells is only known to within a
+// bucket, the cell holds somewhere from energy up to energy + energy_bucket - 1. Orientations between compass points are
+// sent as whole degrees rounded from the 128 steps a cell can face, not the exact degrees it was built or rotated with.
+#[derive(Serialize, Clone)]
+pub struct CellView {
+    pub owner: Option<String>,
+    pub kind: CellKind,
+    pub energy: i16,
+    pub energy_bucket: i16,
+    pub orientation: Orientation,
+}
+
 #[derive(Serialize, Clone)]
 pub enum SquareKind {
-    CritterCell(Cell),
+    CritterCell(CellView),
     WorldCell(u8),
+    TerrainCell(Terrain), // Rock or water, nothing lives or grows there
+}
+
+// How visual packages are encoded for a session, chosen by the client with SetVisualFormat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub enum VisualFormat {
+    Json, // A JSON list of squares, the default
+    Compact, // The binary layout written by encode_compact
+}
+
+impl VisualFormat {
+    pub fn from_input_string(input_string: &str) -> Option<Self> {
+        match input_string {
+            "Json" => Some(VisualFormat::Json),
+            "Compact" => Some(VisualFormat::Compact),
+            _ => None,
+        }
+    }
+}
+
+// Clients that turn envelopes on with SetEnvelope get every JSON package wrapped in one of these, so they can tell a
+// visual package ({"Visual": [...]}) from a sense package ({"Sense": {...}}). Compact visual packages start with "VP"
+// instead. Envelopes are off by default, so older clients keep getting the bare list of squares they always have.
+#[derive(Serialize)]
+pub enum Envelope<'a> {
+    Visual(&'a [Square]),
+    Sense(&'a SensePkg),
 }
 
+// Bumped whenever the compact layout changes, so clients can tell which layout they were sent
+pub const COMPACT_VERSION: u8 = 2;
+const COMPACT_MAGIC: &[u8; 2] = b"VP";
+
 #[derive(Debug, Serialize, Clone)]
 pub struct Point {
     pub x: i32,
     pub y: i32,
 }
 
-pub fn generate_visual_pkg(world_data: &WorldData, soul_id: &String, x: &i32, y: &i32, radius: i32, direction: &String, angle_deg: &i16) -> Vec<u8> {
+// Every square the eye at (x, y) can see when activated with `power`, in the soul's local coordinates. More power sees
+// further and reads other critters' energy more precisely. Encode the result with encode_visual_pkg.
+pub fn generate_visual_pkg(world_data: &WorldData, soul_id: &String, x: &i32, y: &i32, power: i16, direction: Orientation, b_ps: &BPs) -> Vec<Square> {
+
+    let radius = if direction == Orientation::C {
+        (power as f32 / b_ps.C_AEtoAction_cent as f32).round() as i32
+    } else {
+        (power as f32 / b_ps.C_AEtoAction_dir as f32).round() as i32
+    };
+    let bucket = energy_bucket(power, b_ps);
+    let observer = world_data.souls.handle(soul_id);
 
     let mut visual_pkg = Vec::new();
-    let points = circle_slice((x, y), radius, direction, angle_deg);
+    let points = circle_slice((x, y), radius, direction, &b_ps.DirectionalEyeballFOV);
+
+    let mut seen = HashSet::new();
 
     for point in points {
-        //If the generated point is not in the world bounds dont generate a square pkg for it.
-        if !world_data.is_in_bounds(point.x, point.y) {
+        //If the generated point is off the edge of the world dont generate a square pkg for it. On a wrapping world it
+        //wraps around instead, and a square that shows up twice in a small world is only sent once.
+        let Some((gx, gy)) = world_data.resolve(point.x, point.y) else {
+            continue;
+        };
+        if !seen.insert((gx, gy)) || !line_of_sight(world_data, soul_id, (*x, *y), (point.x, point.y), &b_ps.OpaqueCells) {
             continue;
         }
 
-        let (local_x, local_y) = world_data.global_to_local(soul_id, point.x, point.y);
+        let (local_x, local_y) = world_data.global_to_local(soul_id, gx as i32, gy as i32);
 
         let mut square = Square {x: local_x, y: local_y, content: SquareKind::WorldCell(0)};
-        if world_data.is_critter_at(point.x, point.y) {
-            square.content = SquareKind::CritterCell(world_data.critter_layer[point.x as usize][point.y as usize].clone());
+        if !world_data.terrain[(gx, gy)].is_passable() {
+            square.content = SquareKind::TerrainCell(world_data.terrain[(gx, gy)]);
+        } else if world_data.is_critter_at(gx as i32, gy as i32) {
+            let cell = world_data.critter_layer.get((gx, gy));
+            square.content = SquareKind::CritterCell(if Some(cell.owner) == observer {
+                CellView {owner: Some("self".to_string()), kind: cell.kind, energy: cell.energy, energy_bucket: 1, orientation: cell.orientation}
+            } else {
+                CellView {
+                    owner: (cell.owner != SoulHandle::NONE).then(|| owner_tag(world_data, soul_id, world_data.souls.name(cell.owner))),
+                    kind: cell.kind,
+                    energy: cell.energy.div_euclid(bucket) * bucket,
+                    energy_bucket: bucket,
+                    orientation: cell.orientation,
+                }
+            });
         } else {
-            square.content = SquareKind::WorldCell(world_data.world[point.x as usize][point.y as usize].clone());
+            square.content = SquareKind::WorldCell(world_data.world[(gx, gy)]);
         }
 
         visual_pkg.push(square);
     }
 
-    let json_visual_pkg: Vec<u8> = serde_json::to_vec(&visual_pkg)
-        .expect("Failed to serialize visual_pkg");
+    visual_pkg
+}
+
+// Width of the energy buckets an Eyeball activated with `power` reads other critters' cells in
+pub fn energy_bucket(power: i16, b_ps: &BPs) -> i16 {
+    let narrowed = power.max(0) / b_ps.C_AEtoBucket.max(1);
+    b_ps.EnergyBucket.saturating_sub(narrowed).max(b_ps.MinEnergyBucket).max(1)
+}
+
+// Tag an observer sees another soul's cells under. It stays the same from one package to the next so critters can be
+// told apart and followed, but it gives away nothing about the soul id, and different players see different tags.
+// Offspring see the same tags as the rest of their lineage, since they are controlled by the same client. The tags are a
+// SipHash-2-4 keyed with the world's tag_key, so they stay the same for a saved world whatever toolchain built the server.
+pub fn owner_tag(world_data: &WorldData, observer: &str, owner: &str) -> String {
+    let (key0, key1) = world_data.tag_key;
+    let mut hasher = SipHasher24::new_with_keys(key0, key1);
+    for id in [lifecycle::root_of(world_data, observer), owner] {
+        hasher.write(&(id.len() as u64).to_le_bytes());
+        hasher.write(id.as_bytes());
+    }
+    format!("{:08x}", hasher.finish() as u32)
+}
+
+pub fn encode_visual_pkg(visual_pkg: &[Square], format: VisualFormat, enveloped: bool) -> Vec<u8> {
+    match format {
+        VisualFormat::Json if enveloped => serde_json::to_vec(&Envelope::Visual(visual_pkg)).expect("Failed to serialize visual_pkg"),
+        VisualFormat::Json => serde_json::to_vec(visual_pkg).expect("Failed to serialize visual_pkg"),
+        VisualFormat::Compact => encode_compact(visual_pkg),
+    }
+}
+
+// Compact binary layout, all numbers little endian:
+//   "VP", version u8
+//   left i16, top i16, width u16, height u16: bounding box of the squares in local coordinates, top is the largest y
+//   palette: count u16, then per owner tag a length u16 and the UTF-8 bytes
+//   bitmask: one bit per square of the box, row by row from the top and left to right within a row, lowest bit first.
+//            A set bit means the square is in the package.
+//   one record per set bit, in bitmask order, starting with a tag byte:
+//     0 world square: food u8
+//     1 terrain square: terrain u8 (Open 0, Soil 1, Rock 2, Water 3)
+//     2 critter cell: palette index u16 (0xFFFF for a corpse), kind u8 (CellKind in declaration order, Empty 0),
+//       energy i16, energy bucket u16, bearing u16 (degrees clockwise from north, 0xFFFF for a centered cell)
+pub fn encode_compact(visual_pkg: &[Square]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(COMPACT_MAGIC);
+    bytes.push(COMPACT_VERSION);
+
+    let left = visual_pkg.iter().map(|square| square.x).min().unwrap_or(0);
+    let right = visual_pkg.iter().map(|square| square.x).max().unwrap_or(-1);
+    let top = visual_pkg.iter().map(|square| square.y).max().unwrap_or(0);
+    let bottom = visual_pkg.iter().map(|square| square.y).min().unwrap_or(1);
+    let (width, height) = ((right - left + 1) as usize, (top - bottom + 1) as usize);
+    bytes.extend_from_slice(&(left as i16).to_le_bytes());
+    bytes.extend_from_slice(&(top as i16).to_le_bytes());
+    bytes.extend_from_slice(&(width as u16).to_le_bytes());
+    bytes.extend_from_slice(&(height as u16).to_le_bytes());
+
+    // Squares sorted into bitmask order, each owner tag is written once and referred to by its index
+    let mut ordered: Vec<(usize, &Square)> = visual_pkg.iter()
+        .map(|square| ((top - square.y) as usize * width + (square.x - left) as usize, square))
+        .collect();
+    ordered.sort_by_key(|(index, _)| *index);
+
+    let mut palette: Vec<&str> = Vec::new();
+    for (_, square) in &ordered {
+        if let SquareKind::CritterCell(CellView {owner: Some(owner), ..}) = &square.content && !palette.contains(&owner.as_str()) {
+            palette.push(owner);
+        }
+    }
+    bytes.extend_from_slice(&(palette.len() as u16).to_le_bytes());
+    for id in &palette {
+        bytes.extend_from_slice(&(id.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(id.as_bytes());
+    }
+
+    let mut bitmask = vec![0u8; (width * height).div_ceil(8)];
+    for (index, _) in &ordered {
+        bitmask[index / 8] |= 1 << (index % 8);
+    }
+    bytes.extend_from_slice(&bitmask);
+
+    for (_, square) in ordered {
+        match &square.content {
+            SquareKind::WorldCell(food) => {
+                bytes.push(0);
+                bytes.push(*food);
+            }
+            SquareKind::TerrainCell(terrain) => {
+                bytes.push(1);
+                bytes.push(*terrain as u8);
+            }
+            SquareKind::CritterCell(cell) => {
+                bytes.push(2);
+                let index = cell.owner.as_ref().and_then(|owner| palette.iter().position(|tag| tag == owner));
+                bytes.extend_from_slice(&index.map_or(u16::MAX, |index| index as u16).to_le_bytes());
+                bytes.push(cell.kind as u8);
+                bytes.extend_from_slice(&cell.energy.to_le_bytes());
+                bytes.extend_from_slice(&(cell.energy_bucket as u16).to_le_bytes());
+                bytes.extend_from_slice(&cell.orientation.bearing().unwrap_or(u16::MAX).to_le_bytes());
+            }
+        }
+    }
+
+    bytes
+}
+
+// True if nothing opaque lies on the squares strictly between the eye and the target. The target itself is always
+// visible, so the face of a rock or an Armor plate can be seen but not what is behind it. Opaque terrain always blocks,
+// critter cells block if their kind is in opaque_cells, and an eye always sees past the cells of its own critter.
+pub fn line_of_sight(world_data: &WorldData, soul_id: &str, from: (i32, i32), to: (i32, i32), opaque_cells: &[CellKind]) -> bool {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let steps = dx.abs().max(dy.abs());
+    let observer = world_data.souls.handle(soul_id);
 
-    json_visual_pkg
+    for i in 1..steps {
+        let px = from.0 + (dx as f32 * i as f32 / steps as f32).round() as i32;
+        let py = from.1 + (dy as f32 * i as f32 / steps as f32).round() as i32;
+        let Some(square) = world_data.resolve(px, py) else {
+            continue;
+        };
+        if world_data.terrain[square].is_opaque() {
+            return false;
+        }
+
+        let kind = world_data.critter_layer.kind(square);
+        if opaque_cells.contains(&kind) && Some(world_data.critter_layer.owner(square)) != observer {
+            return false;
+        }
+    }
+    true
 }
 
 pub fn circle_slice(
     center: (&i32, &i32),
     radius: i32,
-    direction: &String,
+    direction: Orientation,
     angle_deg: &i16,
 ) -> Vec<Point> {
     let (cx, cy) = center;
     let mut points = Vec::new();
-    let full_circle = *angle_deg >= 360;
-
-    // Convert direction to radians for centerline
-    let dir_rad = match direction.as_str() {
-        "N" | "n" => PI / 2.0,
-        "S" | "s" => -PI / 2.0,
-        "E" | "e" => 0.0,
-        "W" | "w" => PI,
-        _ => 0.0,
-    };
+    // Centered eyes look all the way around
+    let full_circle = *angle_deg >= 360 || direction.bearing().is_none();
+
+    // Convert the compass bearing to a counterclockwise angle from east for the centerline
+    let dir_rad = (90.0 - direction.bearing().unwrap_or(90) as f32).to_radians();
 
     let half_angle_rad = (*angle_deg as f32).to_radians() / 2.0;
 
@@ -88,7 +290,8 @@ pub fn circle_slice(
                     continue;
                 }
 
-                let mut angle = dy.atan2(dx);
+                // Global y grows southwards, flip it so the angle matches the compass directions above
+                let angle = (-dy).atan2(dx);
                 let mut diff = angle - dir_rad;
 
                 while diff > PI {
@@ -106,4 +309,188 @@ pub fn circle_slice(
     }
 
     points
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell_def::Cell;
+
+    const KINDS: [CellKind; 13] = [
+        CellKind::Empty, CellKind::Soul, CellKind::Tissue, CellKind::Eyeball, CellKind::Mouth, CellKind::Butt, CellKind::Muscle,
+        CellKind::Anchor, CellKind::Armor, CellKind::Corpse, CellKind::Nose, CellKind::Whisker, CellKind::Ear,
+    ];
+    const TERRAIN: [Terrain; 4] = [Terrain::Open, Terrain::Soil, Terrain::Rock, Terrain::Water];
+
+    // Reads the layout documented above encode_compact back into squares, the way a client would
+    fn decode_compact(bytes: &[u8]) -> Vec<Square> {
+        let mut at = 0;
+        let mut take = |n: usize| {
+            at += n;
+            &bytes[at - n..at]
+        };
+        assert_eq!(take(2), COMPACT_MAGIC);
+        assert_eq!(take(1)[0], COMPACT_VERSION);
+        let u16_at = |b: &[u8]| u16::from_le_bytes([b[0], b[1]]);
+        let left = i16::from_le_bytes(take(2).try_into().unwrap()) as i32;
+        let top = i16::from_le_bytes(take(2).try_into().unwrap()) as i32;
+        let width = u16_at(take(2)) as usize;
+        let height = u16_at(take(2)) as usize;
+
+        let palette: Vec<String> = (0..u16_at(take(2)))
+            .map(|_| {
+                let len = u16_at(take(2)) as usize;
+                String::from_utf8(take(len).to_vec()).unwrap()
+            })
+            .collect();
+        let bitmask = take((width * height).div_ceil(8)).to_vec();
+
+        let mut squares = Vec::new();
+        for index in (0..width * height).filter(|index| bitmask[index / 8] & (1 << (index % 8)) != 0) {
+            let (x, y) = (left + (index % width) as i32, top - (index / width) as i32);
+            let content = match take(1)[0] {
+                0 => SquareKind::WorldCell(take(1)[0]),
+                1 => SquareKind::TerrainCell(TERRAIN[take(1)[0] as usize]),
+                _ => {
+                    let owner = u16_at(take(2));
+                    let kind = KINDS[take(1)[0] as usize];
+                    let energy = i16::from_le_bytes(take(2).try_into().unwrap());
+                    let energy_bucket = u16_at(take(2)) as i16;
+                    let bearing = u16_at(take(2));
+                    SquareKind::CritterCell(CellView {
+                        owner: (owner != u16::MAX).then(|| palette[owner as usize].clone()),
+                        kind,
+                        energy,
+                        energy_bucket,
+                        orientation: if bearing == u16::MAX { Orientation::C } else { Orientation::from_bearing(bearing) },
+                    })
+                }
+            };
+            squares.push(Square { x, y, content });
+        }
+        assert_eq!(at, bytes.len());
+        squares
+    }
+
+    fn critter(owner: Option<&str>, kind: CellKind, energy: i16, orientation: Orientation) -> SquareKind {
+        SquareKind::CritterCell(CellView {owner: owner.map(str::to_string), kind, energy, energy_bucket: 50, orientation})
+    }
+
+    #[test]
+    fn empty_package_round_trips() {
+        let bytes = encode_compact(&[]);
+        assert_eq!(bytes, [b'V', b'P', COMPACT_VERSION, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(decode_compact(&bytes).is_empty());
+    }
+
+    #[test]
+    fn squares_are_written_in_bitmask_order() {
+        // Row by row from the top, left to right within a row, whatever order the squares came in
+        let squares = vec![
+            Square {x: 0, y: -1, content: SquareKind::WorldCell(30)},
+            Square {x: 1, y: 0, content: SquareKind::WorldCell(20)},
+            Square {x: 0, y: 0, content: SquareKind::WorldCell(10)},
+        ];
+        let bytes = encode_compact(&squares);
+        assert_eq!(bytes[3..13], [0, 0, 0, 0, 2, 0, 2, 0, 0, 0]); // left 0, top 0, 2x2, empty palette
+        assert_eq!(bytes[13], 0b0111);
+        assert_eq!(bytes[14..], [0, 10, 0, 20, 0, 30]);
+
+        let decoded: Vec<(i32, i32)> = decode_compact(&bytes).iter().map(|square| (square.x, square.y)).collect();
+        assert_eq!(decoded, vec![(0, 0), (1, 0), (0, -1)]);
+    }
+
+    #[test]
+    fn every_kind_of_square_round_trips() {
+        let squares = vec![
+            Square {x: -2, y: 3, content: critter(Some("self"), CellKind::Soul, 900, Orientation::C)},
+            Square {x: 4, y: 3, content: critter(Some("1a2b3c4d"), CellKind::Eyeball, 300, Orientation::from_bearing(101))},
+            Square {x: -1, y: -4, content: critter(None, CellKind::Corpse, 50, Orientation::C)},
+            Square {x: 0, y: 0, content: critter(Some("1a2b3c4d"), CellKind::Ear, 100, Orientation::SW)},
+            Square {x: 3, y: -4, content: SquareKind::TerrainCell(Terrain::Water)},
+            Square {x: 1, y: 1, content: SquareKind::WorldCell(255)},
+        ];
+        let mut expected = squares.clone();
+        expected.sort_by_key(|square| (-square.y, square.x));
+
+        let decoded = decode_compact(&encode_compact(&squares));
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), serde_json::to_string(&expected).unwrap());
+    }
+
+    // Clients that never asked for envelopes keep getting the bare list of squares
+    #[test]
+    fn json_is_only_enveloped_on_request() {
+        let squares = vec![Square {x: 1, y: -1, content: SquareKind::WorldCell(7)}];
+        let bare = r#"[{"x":1,"y":-1,"content":{"WorldCell":7}}]"#;
+        assert_eq!(encode_visual_pkg(&squares, VisualFormat::Json, false), bare.as_bytes());
+        assert_eq!(encode_visual_pkg(&squares, VisualFormat::Json, true), format!(r#"{{"Visual":{}}}"#, bare).as_bytes());
+        assert!(encode_visual_pkg(&squares, VisualFormat::Compact, true).starts_with(COMPACT_MAGIC));
+    }
+
+    // A radius 20 centered eye sees 1257 squares, the compact layout should be a small fraction of the JSON
+    #[test]
+    fn compact_is_much_smaller_than_json() {
+        let b_ps = BPs::new();
+        let mut world_data = WorldData::new(64, 64);
+        let owner = world_data.souls.intern("observer");
+        world_data.critter_layer.set((32, 32), Cell::new(owner, CellKind::Soul, 1000, Orientation::C));
+        world_data.soul_locations.push(("observer".to_string(), 32, 32));
+        for x in 0..64 {
+            for y in 0..64 {
+                world_data.world[(x, y)] = ((x * 7 + y * 3) % 256) as u8;
+            }
+        }
+
+        let power = 20 * b_ps.C_AEtoAction_cent;
+        let squares = generate_visual_pkg(&world_data, &"observer".to_string(), &32, &32, power, Orientation::C, &b_ps);
+        let json = encode_visual_pkg(&squares, VisualFormat::Json, false).len();
+        let compact = encode_visual_pkg(&squares, VisualFormat::Compact, false).len();
+        println!("{} squares: JSON {} bytes, compact {} bytes", squares.len(), json, compact);
+        assert!(compact * 10 < json);
+    }
+
+    // An eye at (2, 5) looking east past whatever sits on (4, 5) at a target on (6, 5)
+    #[test]
+    fn opaque_cells_hide_what_is_behind_them() {
+        let mut world_data = WorldData::new(10, 10);
+        let observer = world_data.souls.intern("observer");
+        let other = world_data.souls.intern("other");
+        let opaque = [CellKind::Armor];
+        assert!(line_of_sight(&world_data, "observer", (2, 5), (6, 5), &opaque));
+
+        world_data.critter_layer.set((4, 5), Cell::new(other, CellKind::Armor, 100, Orientation::C));
+        assert!(!line_of_sight(&world_data, "observer", (2, 5), (6, 5), &opaque));
+        assert!(line_of_sight(&world_data, "observer", (2, 5), (4, 5), &opaque)); // The plate itself is visible
+        assert!(line_of_sight(&world_data, "observer", (2, 5), (6, 7), &opaque)); // Off the blocked line
+
+        world_data.critter_layer.set((4, 5), Cell::new(other, CellKind::Tissue, 100, Orientation::C));
+        assert!(line_of_sight(&world_data, "observer", (2, 5), (6, 5), &opaque));
+
+        world_data.critter_layer.set((4, 5), Cell::new(observer, CellKind::Armor, 100, Orientation::C));
+        assert!(line_of_sight(&world_data, "observer", (2, 5), (6, 5), &opaque)); // Own cells never block
+    }
+
+    #[test]
+    fn rock_blocks_sight_and_water_does_not() {
+        let mut world_data = WorldData::new(10, 10);
+        world_data.terrain[(4, 5)] = Terrain::Water;
+        assert!(line_of_sight(&world_data, "observer", (2, 5), (6, 5), &[]));
+
+        world_data.terrain[(4, 5)] = Terrain::Rock;
+        assert!(!line_of_sight(&world_data, "observer", (2, 5), (6, 5), &[]));
+        assert!(line_of_sight(&world_data, "observer", (2, 5), (4, 5), &[]));
+    }
+
+    // Tags of a saved world must not change when the server is rebuilt, so they are pinned to known values
+    #[test]
+    fn owner_tags_are_stable() {
+        let mut world_data = WorldData::new(4, 4);
+        world_data.tag_key = (1, 2);
+        assert_eq!(owner_tag(&world_data, "alice", "bob"), "6b49771c");
+        world_data.parents.insert("offspring".to_string(), "alice".to_string());
+        assert_eq!(owner_tag(&world_data, "offspring", "bob"), owner_tag(&world_data, "alice", "bob"));
+        assert_ne!(owner_tag(&world_data, "carol", "bob"), owner_tag(&world_data, "alice", "bob"));
+
+        world_data.tag_key = (3, 4);
+        assert_ne!(owner_tag(&world_data, "alice", "bob"), "6b49771c");
+    }
+}
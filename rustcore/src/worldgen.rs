@@ -0,0 +1,198 @@
+// This file houses the terrain generator. Layered gradient noise decides the biome and terrain of every square and how much
+// food it can hold.
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Serialize, Deserialize};
+
+use crate::BPs;
+use crate::utils;
+use crate::chunks::ChunkedGrid;
+
+// Broad kind of land a square belongs to, decided once when the world is generated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+pub enum Biome {
+    Valley, // Wet lowland, the most fertile ground
+    Grassland,
+    Desert, // Dry ground that holds little food
+    Wall, // High rock, always Rock terrain
+    Water, // Deep water, always Water terrain
+}
+
+// What physically covers a square, fixed when the world is generated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+pub enum Terrain {
+    Open, // Plain ground
+    Soil, // Fertile ground, food regrows faster
+    Rock, // Impassable and blocks line of sight
+    Water, // Impassable, but can be seen across
+}
+
+impl Terrain {
+    // Critters can neither be built on nor move through impassable squares
+    pub fn is_passable(self) -> bool {
+        matches!(self, Terrain::Open | Terrain::Soil)
+    }
+
+    // Eyeballs cannot see past opaque squares
+    pub fn is_opaque(self) -> bool {
+        self == Terrain::Rock
+    }
+
+    // Single character used when printing the map to the console, Open ground shows its biome instead
+    pub fn symbol(self) -> Option<char> {
+        match self {
+            Terrain::Open => None,
+            Terrain::Soil => Some('%'),
+            Terrain::Rock => Some('#'),
+            Terrain::Water => Some('~'),
+        }
+    }
+}
+
+impl Biome {
+    // Single character used when printing the biome map to the console
+    pub fn symbol(self) -> char {
+        match self {
+            Biome::Valley => '"',
+            Biome::Grassland => '.',
+            Biome::Desert => ' ',
+            Biome::Wall => '#',
+            Biome::Water => '~',
+        }
+    }
+}
+
+// Seeded 2D gradient noise, the permutation table is shuffled from the world's RNG
+pub struct Noise {
+    perm: Vec<usize>,
+}
+
+impl Noise {
+    pub fn new(rng: &mut impl Rng) -> Self {
+        let mut perm: Vec<usize> = (0..256).collect();
+        perm.shuffle(rng);
+        perm.extend_from_within(..);
+        Self { perm }
+    }
+
+    // Gradient noise at (x, y), roughly in -1..1 and smooth between whole coordinates
+    pub fn sample(&self, x: f32, y: f32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (xi, yi) = ((x0 as i32 & 255) as usize, (y0 as i32 & 255) as usize);
+
+        let corner = |cx: usize, cy: usize, dx: f32, dy: f32| {
+            let hash = self.perm[self.perm[xi + cx] + yi + cy];
+            gradient(hash, dx, dy)
+        };
+
+        let (u, v) = (fade(fx), fade(fy));
+        let top = lerp(corner(0, 0, fx, fy), corner(1, 0, fx - 1.0, fy), u);
+        let bottom = lerp(corner(0, 1, fx, fy - 1.0), corner(1, 1, fx - 1.0, fy - 1.0), u);
+        lerp(top, bottom, v)
+    }
+
+    // Several octaves of noise stacked on top of each other, each finer and fainter than the last. Stays in -1..1.
+    // The largest features are about `scale` squares across.
+    pub fn fractal(&self, x: f32, y: f32, scale: i16, b_ps: &BPs) -> f32 {
+        let persistence = b_ps.TerrainPersistence as f32 / 100.0;
+        let lacunarity = b_ps.TerrainLacunarity as f32 / 100.0;
+
+        let mut frequency = 1.0 / (scale.max(1) as f32);
+        let mut amplitude = 1.0;
+        let mut total = 0.0;
+        let mut max_total = 0.0;
+
+        for _ in 0..b_ps.TerrainOctaves.max(1) {
+            total += self.sample(x * frequency, y * frequency) * amplitude;
+            max_total += amplitude;
+            frequency *= lacunarity;
+            amplitude *= persistence;
+        }
+
+        (total / max_total).clamp(-1.0, 1.0)
+    }
+
+    // Fractal noise at a square of a width x height world. On a wrapping world the copies of the field shifted by one
+    // world width and height are blended in, so the terrain meets up seamlessly across the edges.
+    pub fn at_square(&self, x: usize, y: usize, width: usize, height: usize, wrap: bool, scale: i16, b_ps: &BPs) -> f32 {
+        let (x, y) = (x as f32, y as f32);
+        if !wrap {
+            return self.fractal(x, y, scale, b_ps);
+        }
+
+        let (w, h) = (width as f32, height as f32);
+        let (u, v) = (x / w, y / h);
+        self.fractal(x, y, scale, b_ps) * (1.0 - u) * (1.0 - v)
+            + self.fractal(x - w, y, scale, b_ps) * u * (1.0 - v)
+            + self.fractal(x, y - h, scale, b_ps) * (1.0 - u) * v
+            + self.fractal(x - w, y - h, scale, b_ps) * u * v
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+// Dot product of the offset with one of eight unit gradients picked by the hash
+fn gradient(hash: usize, dx: f32, dy: f32) -> f32 {
+    const DIAGONAL: f32 = std::f32::consts::FRAC_1_SQRT_2;
+    match hash & 7 {
+        0 => dx,
+        1 => -dx,
+        2 => dy,
+        3 => -dy,
+        4 => (dx + dy) * DIAGONAL,
+        5 => (dx - dy) * DIAGONAL,
+        6 => (-dx + dy) * DIAGONAL,
+        _ => (-dx - dy) * DIAGONAL,
+    }
+}
+
+// Elevation and moisture are both in -1..1, the thresholds in config.toml are percentages of that range
+pub fn classify(elevation: f32, moisture: f32, b_ps: &BPs) -> Biome {
+    let elevation = elevation * 100.0;
+    let moisture = moisture * 100.0;
+
+    if elevation < b_ps.WaterLevel as f32 {
+        Biome::Water
+    } else if elevation > b_ps.WallLevel as f32 {
+        Biome::Wall
+    } else if moisture > b_ps.ValleyMoisture as f32 {
+        Biome::Valley
+    } else if moisture < b_ps.DesertMoisture as f32 {
+        Biome::Desert
+    } else {
+        Biome::Grassland
+    }
+}
+
+// Walls and water always become Rock and Water. Elsewhere the detail noise (-1..1) scatters rock outcrops, and patches
+// of fertile soil in the wetter biomes.
+pub fn place_terrain(biome: Biome, detail: f32, b_ps: &BPs) -> Terrain {
+    let detail = detail * 100.0;
+
+    match biome {
+        Biome::Wall => Terrain::Rock,
+        Biome::Water => Terrain::Water,
+        _ if detail > b_ps.RockOutcrops as f32 => Terrain::Rock,
+        Biome::Valley | Biome::Grassland if detail < b_ps.SoilPatches as f32 => Terrain::Soil,
+        _ => Terrain::Open,
+    }
+}
+
+pub fn visualize_terrain_console(biomes: &ChunkedGrid<Biome>, terrain: &ChunkedGrid<Terrain>, max_size: i16) {
+    let step = utils::console_step(terrain.width(), terrain.height(), max_size);
+    for y in (0..terrain.height()).step_by(step) {
+        let line: String = (0..terrain.width()).step_by(step)
+            .map(|x| terrain[(x, y)].symbol().unwrap_or(biomes[(x, y)].symbol()))
+            .collect();
+        println!("{}", line);
+    }
+}